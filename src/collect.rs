@@ -0,0 +1,418 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::platform;
+use crate::{Entry, WalkDir, WalkError};
+
+struct BySize(u64, Entry);
+
+impl PartialEq for BySize {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for BySize {}
+
+impl PartialOrd for BySize {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BySize {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl WalkDir {
+    pub fn leaves_only(self) -> Result<Vec<Entry>, WalkError> {
+        let follow = self.is_following_links();
+        let entries = self.collect::<Result<Vec<_>, _>>()?;
+        let parents: HashSet<&Path> = entries.iter().filter_map(|e| e.path().parent()).collect();
+        let mut leaves = Vec::new();
+        for entry in &entries {
+            let is_dir = if follow {
+                entry.metadata()?.is_dir()
+            } else {
+                entry.file_type()?.is_dir()
+            };
+            // A directory the walk never opened has to be checked on disk.
+            let leaf = if !is_dir {
+                true
+            } else if entry.descended() {
+                !parents.contains(entry.path())
+            } else {
+                fs::read_dir(entry.real_path()).is_ok_and(|mut rd| rd.next().is_none())
+            };
+            if leaf {
+                leaves.push(entry.clone());
+            }
+        }
+        Ok(leaves)
+    }
+
+    pub fn metadata_map(self) -> Result<HashMap<PathBuf, fs::Metadata>, WalkError> {
+        let follow = self.is_following_links();
+        let mut map = HashMap::new();
+        for entry in self {
+            let entry = entry?;
+            let md = if follow {
+                entry.metadata()?
+            } else {
+                entry.symlink_metadata()?
+            };
+            map.insert(entry.path().to_path_buf(), md);
+        }
+        Ok(map)
+    }
+
+    /// Buffers the entire walk in memory before reversing it.
+    pub fn collect_rev(self) -> Result<Vec<Entry>, WalkError> {
+        let mut entries = self.collect::<Result<Vec<_>, _>>()?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    pub fn common_ancestor(self) -> Result<Option<PathBuf>, WalkError> {
+        let follow = self.is_following_links();
+        let mut common: Option<PathBuf> = None;
+        for entry in self {
+            let entry = entry?;
+            let is_dir = if follow {
+                entry.metadata()?.is_dir()
+            } else {
+                entry.file_type()?.is_dir()
+            };
+            // A file is never its own ancestor; start from the directory holding it.
+            let dir = match entry.path().parent() {
+                Some(parent) if !is_dir => parent,
+                _ => entry.path(),
+            };
+            common = Some(match common {
+                None => dir.to_path_buf(),
+                Some(prefix) => prefix
+                    .components()
+                    .zip(dir.components())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect(),
+            });
+        }
+        Ok(common)
+    }
+
+    pub fn sorted_by_path(self) -> Result<Vec<Entry>, WalkError> {
+        let mut entries = self.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(entries)
+    }
+
+    pub fn partition(self) -> Result<(Vec<Entry>, Vec<Entry>), WalkError> {
+        let follow = self.is_following_links();
+        let mut files = Vec::new();
+        // Directory roots are not yielded by the walk itself, unless followed through a link.
+        let mut dirs: Vec<Entry> = if self.opts.skip_root {
+            Vec::new()
+        } else {
+            self.roots
+                .iter()
+                .enumerate()
+                .filter(|(_, r)| r.is_dir() && !(follow && r.is_symlink()))
+                .map(|(i, r)| self.present(Entry::new(r.clone(), 0).with_root_index(i)))
+                .collect()
+        };
+        for entry in self {
+            let entry = entry?;
+            let is_dir = if follow {
+                entry.metadata()?.is_dir()
+            } else {
+                entry.file_type()?.is_dir()
+            };
+            if is_dir {
+                dirs.push(entry);
+            } else {
+                files.push(entry);
+            }
+        }
+        Ok((files, dirs))
+    }
+
+    pub fn collect_links(self) -> Result<Vec<(PathBuf, PathBuf)>, WalkError> {
+        let mut links = Vec::new();
+        for entry in self.follow_links(false).follow_dir_links_only(false) {
+            let entry = entry?;
+            if entry.file_type()?.is_symlink() {
+                let target = fs::read_link(entry.real_path())?;
+                links.push((entry.path().to_path_buf(), target));
+            }
+        }
+        Ok(links)
+    }
+
+    pub fn largest_files(self, n: usize) -> Result<Vec<Entry>, WalkError> {
+        let follow = self.is_following_links();
+        // Min-heap holding the `n` largest files seen so far.
+        let mut heap: BinaryHeap<Reverse<BySize>> = BinaryHeap::with_capacity(n + 1);
+        for entry in self {
+            let entry = entry?;
+            let md = if follow {
+                entry.metadata()?
+            } else {
+                entry.symlink_metadata()?
+            };
+            if !md.is_file() {
+                continue;
+            }
+            heap.push(Reverse(BySize(md.len(), entry)));
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+        Ok(heap.into_sorted_vec().into_iter().map(|Reverse(BySize(_, e))| e).collect())
+    }
+
+    pub fn structure_hash(self) -> Result<u64, WalkError> {
+        let roots = self.roots.clone();
+        let mut hasher = DefaultHasher::new();
+        for entry in self.sorted_by_path()? {
+            let rel = entry
+                .path()
+                .strip_prefix(&roots[entry.root_index()])
+                .unwrap_or(entry.path());
+            rel.hash(&mut hasher);
+            let ft = entry.file_type()?;
+            let kind = if ft.is_dir() {
+                'd'
+            } else if ft.is_file() {
+                'f'
+            } else if ft.is_symlink() {
+                'l'
+            } else {
+                'o'
+            };
+            kind.hash(&mut hasher);
+        }
+        Ok(hasher.finish())
+    }
+
+    // Not named `for_each` so it does not shadow `Iterator::for_each`.
+    pub fn for_each_entry<F>(self, mut f: F) -> Result<(), WalkError>
+    where
+        F: FnMut(Entry),
+    {
+        for entry in self {
+            f(entry?);
+        }
+        Ok(())
+    }
+
+    pub fn try_for_each_entry<F, E>(self, mut f: F) -> Result<(), WalkError>
+    where
+        F: FnMut(Entry) -> Result<(), E>,
+        WalkError: From<E>,
+    {
+        for entry in self {
+            f(entry?)?;
+        }
+        Ok(())
+    }
+
+    pub fn fold_entries<B, F>(self, init: B, mut f: F) -> Result<B, WalkError>
+    where
+        F: FnMut(B, Entry) -> B,
+    {
+        let mut acc = init;
+        for entry in self {
+            acc = f(acc, entry?);
+        }
+        Ok(acc)
+    }
+
+    pub fn extension_counts(self) -> Result<HashMap<OsString, usize>, WalkError> {
+        let follow = self.is_following_links();
+        let mut counts = HashMap::new();
+        for entry in self {
+            let entry = entry?;
+            let is_file = if follow {
+                entry.metadata()?.is_file()
+            } else {
+                entry.file_type()?.is_file()
+            };
+            if is_file {
+                let ext = entry.path().extension().unwrap_or_default().to_os_string();
+                *counts.entry(ext).or_insert(0) += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    pub fn duplicate_names(self) -> Result<HashMap<OsString, Vec<PathBuf>>, WalkError> {
+        let mut by_name: HashMap<OsString, Vec<PathBuf>> = HashMap::new();
+        for entry in self {
+            let entry = entry?;
+            if let Some(name) = entry.path().file_name() {
+                by_name
+                    .entry(name.to_os_string())
+                    .or_default()
+                    .push(entry.path().to_path_buf());
+            }
+        }
+        by_name.retain(|_, paths| paths.len() > 1);
+        Ok(by_name)
+    }
+
+    pub fn hardlink_groups(self) -> Result<Vec<Vec<Entry>>, WalkError> {
+        let follow = self.is_following_links();
+        let mut by_id: HashMap<(u64, u64), Vec<Entry>> = HashMap::new();
+        for entry in self {
+            let entry = entry?;
+            let md = if follow {
+                entry.metadata()?
+            } else {
+                entry.symlink_metadata()?
+            };
+            if md.is_file()
+                && let Some(id) = platform::file_id(entry.path(), &md)
+            {
+                by_id.entry(id).or_default().push(entry);
+            }
+        }
+        let mut groups: Vec<Vec<Entry>> = by_id.into_values().filter(|g| g.len() > 1).collect();
+        groups.sort_by(|a, b| a[0].path().cmp(b[0].path()));
+        Ok(groups)
+    }
+
+    // The caller picks the hash, so collisions are theirs to rule out.
+    pub fn content_duplicates(
+        self,
+        hasher: fn(&Path) -> io::Result<u64>,
+    ) -> Result<Vec<Vec<PathBuf>>, WalkError> {
+        let follow = self.is_following_links();
+        let mut by_hash: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        for entry in self {
+            let entry = entry?;
+            let md = if follow {
+                entry.metadata()?
+            } else {
+                entry.symlink_metadata()?
+            };
+            if md.is_file() {
+                let hash = hasher(entry.real_path())?;
+                by_hash.entry(hash).or_default().push(entry.path().to_path_buf());
+            }
+        }
+        let mut groups: Vec<Vec<PathBuf>> = by_hash.into_values().filter(|g| g.len() > 1).collect();
+        for group in &mut groups {
+            group.sort();
+        }
+        groups.sort();
+        Ok(groups)
+    }
+
+    pub fn broken_symlinks(self) -> Result<Vec<PathBuf>, WalkError> {
+        let mut broken = Vec::new();
+        for entry in self.follow_links(false).follow_dir_links_only(false) {
+            let entry = entry?;
+            if entry.is_symlink()
+                && let Err(e) = fs::metadata(entry.real_path())
+                && e.kind() == io::ErrorKind::NotFound
+            {
+                broken.push(entry.path().to_path_buf());
+            }
+        }
+        Ok(broken)
+    }
+
+    // Links are never followed, so only what lies under the roots is removed.
+    pub fn delete_all(self) -> Result<usize, WalkError> {
+        // `read_dir` would go through a linked root, so such roots are only unlinked.
+        let (linked, mut roots): (Vec<PathBuf>, Vec<PathBuf>) = self
+            .roots
+            .iter()
+            .cloned()
+            .partition(|r| fs::symlink_metadata(r).is_ok_and(|m| m.file_type().is_symlink()));
+        roots.retain(|r| fs::symlink_metadata(r).is_ok());
+
+        let mut entries = Vec::new();
+        if !roots.is_empty() {
+            // Filters and limits set on `self` would leave directories non-empty, so
+            // the walk starts over from the bare roots.
+            let plain = WalkDir::new_multi(&roots)?.max_depth(usize::MAX);
+            for entry in plain {
+                match entry {
+                    Ok(entry) => entries.push(entry.real_path().to_path_buf()),
+                    Err(WalkError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        // Directory roots are not yielded by the walk; they go last.
+        for root in roots {
+            if !entries.contains(&root) {
+                entries.insert(0, root);
+            }
+        }
+        entries.reverse();
+        entries.extend(linked);
+
+        let mut removed = 0;
+        for path in &entries {
+            let result = match fs::symlink_metadata(path) {
+                Ok(md) if md.is_dir() => fs::remove_dir(path),
+                Ok(_) => fs::remove_file(path),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(removed)
+    }
+
+    pub fn write_paths_to<P: AsRef<Path>>(self, out: P) -> Result<usize, WalkError> {
+        let mut writer = BufWriter::new(fs::File::create(out)?);
+        let mut written = 0;
+        for entry in self {
+            let entry = entry?;
+            writeln!(writer, "{}", entry.path().to_string_lossy())?;
+            written += 1;
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Counts with `read_dir` and `DirEntry::file_type` alone, so links are never
+    /// followed and `filter_entry` and the other entry filters do not apply.
+    pub fn quick_count(self) -> Result<usize, WalkError> {
+        let max_depth = self.opts.max_depth;
+        let mut count = 0;
+        for root in &self.roots {
+            // Like the walker, a root that links to a directory is walked through.
+            if !fs::metadata(root)?.is_dir() {
+                count += 1;
+                continue;
+            }
+            let mut pending = vec![(root.clone(), 0)];
+            while let Some((dir, depth)) = pending.pop() {
+                for dirent in fs::read_dir(&dir)? {
+                    let dirent = dirent?;
+                    count += 1;
+                    if depth < max_depth && dirent.file_type()?.is_dir() {
+                        pending.push((dirent.path(), depth + 1));
+                    }
+                }
+            }
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,52 @@
+use std::cmp::Ordering;
+use std::path::PathBuf;
+
+use crate::{WalkDir, WalkError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffItem {
+    OnlyLeft(PathBuf),
+    OnlyRight(PathBuf),
+    Both(PathBuf),
+}
+
+pub fn diff_walk(left: WalkDir, right: WalkDir) -> Result<Vec<DiffItem>, WalkError> {
+    let left = relative_paths(left)?;
+    let right = relative_paths(right)?;
+
+    let mut items = Vec::with_capacity(left.len().max(right.len()));
+    let mut l = left.into_iter().peekable();
+    let mut r = right.into_iter().peekable();
+    loop {
+        let ord = match (l.peek(), r.peek()) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        let item = match ord {
+            Ordering::Less => l.next().map(DiffItem::OnlyLeft),
+            Ordering::Greater => r.next().map(DiffItem::OnlyRight),
+            Ordering::Equal => {
+                r.next();
+                l.next().map(DiffItem::Both)
+            }
+        };
+        items.extend(item);
+    }
+    Ok(items)
+}
+
+fn relative_paths(walker: WalkDir) -> Result<Vec<PathBuf>, WalkError> {
+    let roots = walker.roots.clone();
+    let mut paths = Vec::new();
+    for entry in walker {
+        let entry = entry?;
+        let root = &roots[entry.root_index()];
+        if let Ok(rel) = entry.path().strip_prefix(root) {
+            paths.push(rel.to_path_buf());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
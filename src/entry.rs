@@ -1,35 +1,258 @@
+use std::ffi::OsStr;
 use std::fs;
 use std::io;
-use std::path::{Path, PathBuf};
+use std::path::{Display, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::platform;
 
 #[derive(Debug, Clone)]
 pub struct Entry {
     path: PathBuf,
+    real_path: Option<PathBuf>,
     depth: usize,
+    raw_depth: usize,
+    root_dev: Option<u64>,
+    root_index: usize,
+    truncated: bool,
+    descended: bool,
+    metadata: Option<Box<fs::Metadata>>,
+    file_type: Option<fs::FileType>,
+    via_link: bool,
+    dir_entry: Option<Arc<fs::DirEntry>>,
 }
 
 impl Entry {
     pub fn new(path: PathBuf, depth: usize) -> Self {
-        Self { path, depth }
+        Self {
+            path,
+            real_path: None,
+            depth,
+            raw_depth: depth,
+            root_dev: None,
+            root_index: 0,
+            truncated: false,
+            descended: false,
+            metadata: None,
+            file_type: None,
+            via_link: false,
+            dir_entry: None,
+        }
+    }
+
+    pub(crate) fn with_root_dev(mut self, dev: Option<u64>) -> Self {
+        self.root_dev = dev;
+        self
+    }
+
+    pub(crate) fn with_raw_depth(mut self, raw_depth: usize) -> Self {
+        self.raw_depth = raw_depth;
+        self
+    }
+
+    pub(crate) fn with_root_index(mut self, index: usize) -> Self {
+        self.root_index = index;
+        self
+    }
+
+    pub(crate) fn set_display_path(&mut self, path: PathBuf) {
+        let real = std::mem::replace(&mut self.path, path);
+        self.real_path.get_or_insert(real);
+    }
+
+    pub(crate) fn with_metadata(mut self, md: Option<fs::Metadata>) -> Self {
+        self.metadata = md.map(Box::new);
+        self
+    }
+
+    // The type is the link's own, even when the walk follows it.
+    pub(crate) fn with_file_type(mut self, file_type: Option<fs::FileType>) -> Self {
+        self.file_type = file_type;
+        self
+    }
+
+    pub(crate) fn with_dir_entry(mut self, dirent: fs::DirEntry) -> Self {
+        self.dir_entry = Some(Arc::new(dirent));
+        self
+    }
+
+    pub(crate) fn set_via_link(&mut self, via_link: bool) {
+        self.via_link = via_link;
+    }
+
+    pub(crate) fn via_link(&self) -> bool {
+        self.via_link
+    }
+
+    pub(crate) fn set_truncated(&mut self, truncated: bool) {
+        self.truncated = truncated;
+    }
+
+    pub(crate) fn set_descended(&mut self, descended: bool) {
+        self.descended = descended;
     }
 
     pub fn path(&self) -> &Path {
         &self.path
     }
 
+    pub fn real_path(&self) -> &Path {
+        self.real_path.as_deref().unwrap_or(&self.path)
+    }
+
+    pub fn as_os_str(&self) -> &OsStr {
+        self.path.as_os_str()
+    }
+
+    #[cfg(unix)]
+    pub fn as_bytes(&self) -> &[u8] {
+        use std::os::unix::ffi::OsStrExt;
+        self.path.as_os_str().as_bytes()
+    }
+
+    #[cfg(feature = "unicode-width")]
+    pub fn display_width(&self) -> usize {
+        use unicode_width::UnicodeWidthStr;
+        self.path
+            .file_name()
+            .map_or(0, |name| name.to_string_lossy().width())
+    }
+
+    pub fn display(&self) -> Display<'_> {
+        self.path.display()
+    }
+
+    pub fn ancestors_from_root(&self, root: &Path) -> Vec<PathBuf> {
+        let Ok(rel) = self.path.strip_prefix(root) else {
+            return Vec::new();
+        };
+        // The root itself has no ancestors inside the walk.
+        if rel.as_os_str().is_empty() {
+            return Vec::new();
+        }
+        let mut chain = vec![root.to_path_buf()];
+        let mut components: Vec<_> = rel.components().collect();
+        components.pop();
+        for part in components {
+            let next = chain[chain.len() - 1].join(part);
+            chain.push(next);
+        }
+        chain
+    }
+
     pub fn depth(&self) -> usize {
         self.depth
     }
 
+    pub fn raw_depth(&self) -> usize {
+        self.raw_depth
+    }
+
+    pub fn root_index(&self) -> usize {
+        self.root_index
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn descended(&self) -> bool {
+        self.descended
+    }
+
+    pub fn dir_entry(&self) -> Option<&fs::DirEntry> {
+        self.dir_entry.as_deref()
+    }
+
     pub fn metadata(&self) -> io::Result<fs::Metadata> {
-        fs::metadata(&self.path)
+        match self.metadata {
+            Some(ref md) if !md.file_type().is_symlink() => Ok((**md).clone()),
+            _ => fs::metadata(self.real_path()),
+        }
+    }
+
+    // `File::open` succeeds on directories on Unix; reject them explicitly.
+    pub fn open(&self) -> io::Result<fs::File> {
+        let file = fs::File::open(self.real_path())?;
+        if file.metadata()?.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, "entry is a directory"));
+        }
+        Ok(file)
     }
 
     pub fn symlink_metadata(&self) -> io::Result<fs::Metadata> {
-        fs::symlink_metadata(&self.path)
+        match self.metadata {
+            Some(ref md) => Ok((**md).clone()),
+            None => fs::symlink_metadata(self.real_path()),
+        }
     }
 
     pub fn file_type(&self) -> io::Result<fs::FileType> {
-        fs::symlink_metadata(&self.path).map(|m| m.file_type())
+        match self.file_type {
+            Some(ft) => Ok(ft),
+            None => self.symlink_metadata().map(|m| m.file_type()),
+        }
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.file_type().is_ok_and(|ft| ft.is_symlink())
+    }
+
+    pub fn nlink(&self) -> io::Result<u64> {
+        let md = self.metadata()?;
+        platform::nlink(&md)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "link count not available"))
+    }
+
+    pub fn allocated_size(&self) -> io::Result<u64> {
+        let md = self.metadata()?;
+        platform::allocated(&md)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "block count not available"))
+    }
+
+    pub fn mode(&self) -> io::Result<u32> {
+        let md = self.metadata()?;
+        platform::mode(&md)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "mode bits not available"))
+    }
+
+    pub fn permissions_string(&self) -> io::Result<String> {
+        let mode = self.mode()?;
+        let flags = ['r', 'w', 'x'];
+        Ok((0..9)
+            .map(|i| {
+                if mode & (0o400 >> i) != 0 {
+                    flags[i % 3]
+                } else {
+                    '-'
+                }
+            })
+            .collect())
+    }
+
+    /// Advisory only: permissions can change before the file is actually
+    /// opened. Without the `access` feature this reads the mode bits alone
+    /// and ignores which user the process runs as.
+    pub fn is_readable(&self) -> bool {
+        self.check_access(platform::READ)
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.check_access(platform::WRITE)
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.check_access(platform::EXECUTE)
+    }
+
+    fn check_access(&self, want: u32) -> bool {
+        self.metadata()
+            .is_ok_and(|md| platform::access(self.real_path(), &md, want))
+    }
+
+    pub fn crosses_mount(&self) -> Option<bool> {
+        let root_dev = self.root_dev?;
+        let md = self.symlink_metadata().ok()?;
+        Some(platform::device(&md)? != root_dev)
     }
 }
@@ -1,9 +1,11 @@
-use std::{fmt, io, path::PathBuf};
+use std::{fmt, fs::FileType, io, path::PathBuf};
 
 #[derive(Debug)]
 pub enum WalkError {
     Io(io::Error),
     LoopDetected(PathBuf),
+    UnsupportedRoot(PathBuf, FileType),
+    FilterPanic(PathBuf),
 }
 
 impl From<io::Error> for WalkError {
@@ -12,6 +14,15 @@ impl From<io::Error> for WalkError {
     }
 }
 
+impl From<WalkError> for io::Error {
+    fn from(e: WalkError) -> Self {
+        match e {
+            WalkError::Io(e) => e,
+            other => io::Error::other(other.to_string()),
+        }
+    }
+}
+
 impl fmt::Display for WalkError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -19,6 +30,12 @@ impl fmt::Display for WalkError {
             WalkError::LoopDetected(p) => {
                 write!(f, "Symbolic link loop detected at {}", p.display())
             }
+            WalkError::UnsupportedRoot(p, ft) => {
+                write!(f, "Unsupported root {} ({:?})", p.display(), ft)
+            }
+            WalkError::FilterPanic(p) => {
+                write!(f, "Filter panicked on {}", p.display())
+            }
         }
     }
 }
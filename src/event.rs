@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use crate::{Entry, WalkDir, WalkError};
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Entry(Entry),
+    DirEnd(PathBuf),
+}
+
+pub struct DirMarkers {
+    walker: WalkDir,
+    queue: VecDeque<Result<Event, WalkError>>,
+}
+
+impl DirMarkers {
+    pub(crate) fn new(walker: WalkDir) -> Self {
+        Self {
+            walker,
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Iterator for DirMarkers {
+    type Item = Result<Event, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.queue.is_empty() {
+            let item = self.walker.next();
+            // Directories finish while the walker searches for `item`, so their
+            // markers come first.
+            let mut ends = self.walker.take_dir_ends();
+            // `only_empty` releases a directory as it finishes; its own marker follows it.
+            let held_end = match &item {
+                Some(Ok(entry)) if ends.last().is_some_and(|p| p == entry.real_path()) => ends.pop(),
+                _ => None,
+            };
+            for path in ends {
+                self.queue.push_back(Ok(Event::DirEnd(path)));
+            }
+            if let Some(item) = item {
+                self.queue.push_back(item.map(Event::Entry));
+            }
+            if let Some(path) = held_end {
+                self.queue.push_back(Ok(Event::DirEnd(path)));
+            }
+        }
+        self.queue.pop_front()
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Glob {
+    pattern: Vec<char>,
+    match_path: bool,
+}
+
+impl Glob {
+    pub(crate) fn new(pattern: &str) -> Self {
+        let pattern = pattern.trim_start_matches("./");
+        Self {
+            match_path: pattern.contains('/'),
+            pattern: pattern.chars().collect(),
+        }
+    }
+
+    pub(crate) fn anchored(mut self) -> Self {
+        self.match_path = true;
+        self
+    }
+
+    // Patterns without a slash match the file name at any depth, the rest
+    // match the whole path relative to the root.
+    pub(crate) fn matches(&self, rel_path: &Path) -> bool {
+        let subject = if self.match_path {
+            rel_path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        } else {
+            match rel_path.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => return false,
+            }
+        };
+        let subject: Vec<char> = subject.chars().collect();
+        match_from(&self.pattern, &subject)
+    }
+}
+
+fn match_from(pat: &[char], text: &[char]) -> bool {
+    match pat.first() {
+        None => text.is_empty(),
+        Some('*') if pat.get(1) == Some(&'*') => {
+            let rest = &pat[2..];
+            if rest.first() == Some(&'/') && match_from(&rest[1..], text) {
+                return true;
+            }
+            (0..=text.len()).any(|i| match_from(rest, &text[i..]))
+        }
+        Some('*') => {
+            let rest = &pat[1..];
+            for i in 0..=text.len() {
+                if match_from(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => match text.first() {
+            Some(c) if *c != '/' => match_from(&pat[1..], &text[1..]),
+            _ => false,
+        },
+        Some(p) => match text.first() {
+            Some(c) if c == p => match_from(&pat[1..], &text[1..]),
+            _ => false,
+        },
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::glob::Glob;
+
+struct Rule {
+    glob: Glob,
+    negate: bool,
+    dir_only: bool,
+}
+
+pub(crate) struct IgnoreFile {
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    pub(crate) fn load_gitignore(dir: &Path) -> Option<Self> {
+        let text = fs::read_to_string(dir.join(".gitignore")).ok()?;
+        let rules: Vec<Rule> = text.lines().filter_map(parse_gitignore_line).collect();
+        if rules.is_empty() {
+            return None;
+        }
+        Some(Self {
+            base: dir.to_path_buf(),
+            rules,
+        })
+    }
+
+    pub(crate) fn load_walkignore(dir: &Path) -> Option<Self> {
+        let text = fs::read_to_string(dir.join(".walkignore")).ok()?;
+        let rules: Vec<Rule> = text.lines().filter_map(parse_walkignore_line).collect();
+        if rules.is_empty() {
+            return None;
+        }
+        Some(Self {
+            base: dir.to_path_buf(),
+            rules,
+        })
+    }
+
+    // `None` when no rule in this file matched, so outer files keep their say.
+    fn decide(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let rel = path.strip_prefix(&self.base).ok()?;
+        let mut ignored = None;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            if rule.glob.matches(rel) {
+                ignored = Some(!rule.negate);
+            }
+        }
+        ignored
+    }
+}
+
+pub(crate) fn is_ignored<'a, I>(layers: I, path: &Path, is_dir: bool) -> bool
+where
+    I: IntoIterator<Item = &'a IgnoreFile>,
+{
+    let mut ignored = false;
+    for layer in layers {
+        if let Some(decision) = layer.decide(path, is_dir) {
+            ignored = decision;
+        }
+    }
+    ignored
+}
+
+fn parse_gitignore_line(line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negate, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    if line.is_empty() {
+        return None;
+    }
+
+    let glob = match line.strip_prefix('/') {
+        Some(rest) => Glob::new(rest).anchored(),
+        None => Glob::new(line),
+    };
+    Some(Rule {
+        glob,
+        negate,
+        dir_only,
+    })
+}
+
+// One glob per line; no negation, anchoring or directory-only rules.
+fn parse_walkignore_line(line: &str) -> Option<Rule> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    Some(Rule {
+        glob: Glob::new(line),
+        negate: false,
+        dir_only: false,
+    })
+}
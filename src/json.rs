@@ -0,0 +1,25 @@
+use std::io::{self, Write};
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::{Entry, WalkDir, WalkError};
+
+impl Serialize for Entry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Entry", 2)?;
+        state.serialize_field("path", &self.path().to_string_lossy())?;
+        state.serialize_field("depth", &self.depth())?;
+        state.end()
+    }
+}
+
+impl WalkDir {
+    pub fn write_jsonl<W: Write>(self, w: &mut W) -> Result<(), WalkError> {
+        for entry in self {
+            let entry = entry?;
+            serde_json::to_writer(&mut *w, &entry).map_err(io::Error::from)?;
+            w.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
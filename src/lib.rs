@@ -1,12 +1,29 @@
+mod collect;
+mod diff;
 mod entry;
 mod error;
+mod event;
+mod glob;
+mod ignore;
+#[cfg(feature = "serde")]
+mod json;
+mod manifest;
 mod options;
+mod platform;
+mod raw;
+mod sort;
+mod tree;
 mod walker;
 
+pub use diff::{diff_walk, DiffItem};
 pub use entry::Entry;
 pub use error::WalkError;
-pub use options::WalkOptions;
+pub use event::{DirMarkers, Event};
+pub use manifest::ManifestEntry;
+pub use options::{LoopMode, MetadataErrorPolicy, WalkOptions};
+pub use raw::{RawEntry, RawWalk};
+pub use tree::{NestedDir, SizedNode};
 pub use walker::WalkDir;
 
-#[cfg(test)]
+#[cfg(all(test, unix))]
 mod tests;
@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::{WalkDir, WalkError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub rel_path: PathBuf,
+    pub size: u64,
+    pub mtime: SystemTime,
+}
+
+impl WalkDir {
+    pub fn manifest(self) -> Result<Vec<ManifestEntry>, WalkError> {
+        let roots = self.roots.clone();
+        let follow = self.is_following_links();
+        let mut manifest = Vec::new();
+
+        for entry in self {
+            let entry = entry?;
+            let md = if follow {
+                entry.metadata()?
+            } else {
+                entry.symlink_metadata()?
+            };
+            if !md.is_file() {
+                continue;
+            }
+            let root = &roots[entry.root_index()];
+            let rel_path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            manifest.push(ManifestEntry {
+                rel_path: rel_path.to_path_buf(),
+                size: md.len(),
+                mtime: md.modified()?,
+            });
+        }
+
+        manifest.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+        Ok(manifest)
+    }
+}
@@ -1,14 +1,98 @@
+use std::ffi::OsString;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    #[default]
+    Inode,
+    Canonical,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MetadataErrorPolicy {
+    Skip,
+    #[default]
+    Yield,
+    Abort,
+}
+
 #[derive(Clone, Debug)]
 pub struct WalkOptions {
     pub follow_links: bool,
+    pub follow_dir_links_only: bool,
     pub max_depth: usize,
+    pub byte_budget: Option<u64>,
+    pub track_root_device: bool,
+    pub strict: bool,
+    pub lowercase_names: bool,
+    pub respect_gitignore: bool,
+    pub respect_walkignore: bool,
+    pub dedupe: bool,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub yield_dir_before_open: bool,
+    pub mark_truncated: bool,
+    pub max_file_size: Option<u64>,
+    pub older_than: Option<Duration>,
+    pub resolve_followed_paths: bool,
+    pub keep_dir_entries: bool,
+    pub max_children: Option<usize>,
+    pub max_visited_dirs: Option<usize>,
+    pub descent_fuel: Option<usize>,
+    pub max_path_len: Option<usize>,
+    pub boundary_marker: Option<OsString>,
+    pub metadata_error_policy: MetadataErrorPolicy,
+    pub skip_root: bool,
+    pub loop_mode: LoopMode,
+    pub always_yield_ancestors: bool,
+    pub cwd_relative: bool,
+    pub only_empty: bool,
+    pub skip_unreadable_dirs: bool,
+    pub coalesce_errors: bool,
+    pub posix_relative: bool,
+    pub confine_to_root: bool,
+    pub normalize_nfc: bool,
+    pub catch_filter_panics: bool,
 }
 
 impl Default for WalkOptions {
     fn default() -> Self {
         Self {
             follow_links: false,
+            follow_dir_links_only: false,
             max_depth: 512,
+            byte_budget: None,
+            track_root_device: false,
+            strict: false,
+            lowercase_names: false,
+            respect_gitignore: false,
+            respect_walkignore: false,
+            dedupe: false,
+            uid: None,
+            gid: None,
+            yield_dir_before_open: false,
+            mark_truncated: false,
+            max_file_size: None,
+            older_than: None,
+            resolve_followed_paths: false,
+            keep_dir_entries: false,
+            max_children: None,
+            max_visited_dirs: None,
+            descent_fuel: None,
+            max_path_len: None,
+            boundary_marker: None,
+            metadata_error_policy: MetadataErrorPolicy::Yield,
+            skip_root: false,
+            loop_mode: LoopMode::Inode,
+            always_yield_ancestors: false,
+            cwd_relative: false,
+            only_empty: false,
+            skip_unreadable_dirs: false,
+            coalesce_errors: false,
+            posix_relative: false,
+            confine_to_root: false,
+            normalize_nfc: false,
+            catch_filter_panics: false,
         }
     }
 }
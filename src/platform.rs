@@ -0,0 +1,187 @@
+use std::fs::Metadata;
+use std::path::Path;
+
+#[cfg(unix)]
+pub(crate) fn file_id(_path: &Path, md: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((md.dev(), md.ino()))
+}
+
+// `file_index` and `volume_serial_number` are still unstable on Windows, so
+// the canonical path stands in for the inode while keeping the same shape.
+#[cfg(windows)]
+pub(crate) fn file_id(path: &Path, _md: &Metadata) -> Option<(u64, u64)> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let canonical = std::fs::canonicalize(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    canonical.hash(&mut hasher);
+    Some((0, hasher.finish()))
+}
+
+#[cfg(unix)]
+pub(crate) fn device(md: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(md.dev())
+}
+
+#[cfg(windows)]
+pub(crate) fn device(_md: &Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+pub(crate) fn owner(md: &Metadata) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((md.uid(), md.gid()))
+}
+
+#[cfg(windows)]
+pub(crate) fn owner(_md: &Metadata) -> Option<(u32, u32)> {
+    None
+}
+
+#[cfg(unix)]
+pub(crate) fn nlink(md: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(md.nlink())
+}
+
+#[cfg(windows)]
+pub(crate) fn nlink(_md: &Metadata) -> Option<u64> {
+    None
+}
+
+// `st_blocks` counts 512-byte units regardless of the filesystem block size.
+#[cfg(unix)]
+pub(crate) fn allocated(md: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(md.blocks() * 512)
+}
+
+#[cfg(windows)]
+pub(crate) fn allocated(_md: &Metadata) -> Option<u64> {
+    None
+}
+
+pub(crate) const READ: u32 = 4;
+pub(crate) const WRITE: u32 = 2;
+pub(crate) const EXECUTE: u32 = 1;
+
+// Checked against the effective uid and gid, so root passes read and write
+// checks regardless of the mode bits.
+#[cfg(all(unix, feature = "access"))]
+pub(crate) fn access(path: &Path, _md: &Metadata, want: u32) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mode = match want {
+        READ => libc::R_OK,
+        WRITE => libc::W_OK,
+        _ => libc::X_OK,
+    };
+    // SAFETY: `c_path` is NUL-terminated and outlives the call.
+    unsafe { libc::faccessat(libc::AT_FDCWD, c_path.as_ptr(), mode, libc::AT_EACCESS) == 0 }
+}
+
+// Without `libc` the process ids are unknown, so any class granting the bit counts.
+#[cfg(all(unix, not(feature = "access")))]
+pub(crate) fn access(_path: &Path, md: &Metadata, want: u32) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    md.mode() & (want * 0o111) != 0
+}
+
+#[cfg(windows)]
+pub(crate) fn access(_path: &Path, md: &Metadata, want: u32) -> bool {
+    match want {
+        WRITE => !md.permissions().readonly(),
+        EXECUTE => false,
+        _ => true,
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn mode(md: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(md.mode())
+}
+
+#[cfg(windows)]
+pub(crate) fn mode(_md: &Metadata) -> Option<u32> {
+    None
+}
+
+pub(crate) struct QuickStat {
+    pub(crate) is_dir: bool,
+    pub(crate) is_file: bool,
+    pub(crate) len: u64,
+}
+
+impl QuickStat {
+    fn from_metadata(md: &Metadata) -> Self {
+        Self {
+            is_dir: md.is_dir(),
+            is_file: md.is_file(),
+            len: md.len(),
+        }
+    }
+}
+
+// Asks the kernel for the type and size only, falling back to a full stat
+// when `statx` is unavailable. Old kernels report ENOSYS or EINVAL, and
+// seccomp sandboxes often deny the call with EPERM.
+#[cfg(all(target_os = "linux", feature = "statx"))]
+pub(crate) fn quick_stat(path: &Path, follow: bool) -> std::io::Result<QuickStat> {
+    use std::ffi::CString;
+    use std::io;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let flags = if follow { 0 } else { libc::AT_SYMLINK_NOFOLLOW };
+    let mut buf = MaybeUninit::<libc::statx>::zeroed();
+    // SAFETY: `c_path` is NUL-terminated and `buf` is large enough for a `statx`.
+    let rc = unsafe {
+        libc::statx(
+            libc::AT_FDCWD,
+            c_path.as_ptr(),
+            flags,
+            libc::STATX_TYPE | libc::STATX_SIZE,
+            buf.as_mut_ptr(),
+        )
+    };
+    if rc != 0 {
+        let err = io::Error::last_os_error();
+        if matches!(err.raw_os_error(), Some(libc::ENOSYS | libc::EPERM | libc::EINVAL)) {
+            return std_stat(path, follow);
+        }
+        return Err(err);
+    }
+    // SAFETY: a successful `statx` filled the buffer.
+    let stx = unsafe { buf.assume_init() };
+    let fmt = u32::from(stx.stx_mode) & libc::S_IFMT;
+    Ok(QuickStat {
+        is_dir: fmt == libc::S_IFDIR,
+        is_file: fmt == libc::S_IFREG,
+        len: stx.stx_size,
+    })
+}
+
+#[cfg(not(all(target_os = "linux", feature = "statx")))]
+pub(crate) fn quick_stat(path: &Path, follow: bool) -> std::io::Result<QuickStat> {
+    std_stat(path, follow)
+}
+
+pub(crate) fn std_stat(path: &Path, follow: bool) -> std::io::Result<QuickStat> {
+    let md = if follow {
+        std::fs::metadata(path)?
+    } else {
+        std::fs::symlink_metadata(path)?
+    };
+    Ok(QuickStat::from_metadata(&md))
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, ReadDir};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::platform;
+use crate::{Entry, WalkError, WalkOptions};
+
+#[derive(Debug, Clone)]
+pub struct RawEntry {
+    parent: Rc<PathBuf>,
+    name: OsString,
+    depth: usize,
+}
+
+impl RawEntry {
+    pub fn parent(&self) -> &Path {
+        &self.parent
+    }
+
+    pub fn file_name(&self) -> &OsStr {
+        &self.name
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn to_path_buf(&self) -> PathBuf {
+        self.parent.join(&self.name)
+    }
+
+    pub fn to_entry(&self) -> Entry {
+        Entry::new(self.to_path_buf(), self.depth)
+    }
+}
+
+struct RawFrame {
+    read_dir: ReadDir,
+    dir: Rc<PathBuf>,
+    depth: usize,
+}
+
+// Only honours `follow_links`, `max_depth` and loop detection; filters and
+// sorting configured on the `WalkDir` need full paths and are not applied.
+pub struct RawWalk {
+    roots: std::vec::IntoIter<PathBuf>,
+    opts: WalkOptions,
+    detect_loops: bool,
+    visited: HashSet<(u64, u64)>,
+    stack: Vec<RawFrame>,
+}
+
+impl RawWalk {
+    pub(crate) fn new(roots: Vec<PathBuf>, opts: WalkOptions, detect_loops: bool) -> Self {
+        Self {
+            roots: roots.into_iter(),
+            opts,
+            detect_loops,
+            visited: HashSet::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn start(&mut self, root: PathBuf) -> Option<Result<RawEntry, WalkError>> {
+        let md = match fs::symlink_metadata(&root) {
+            Ok(md) => md,
+            Err(e) => return Some(Err(WalkError::Io(e))),
+        };
+        if md.is_file() {
+            let parent = root.parent().map(Path::to_path_buf).unwrap_or_default();
+            let name = root.file_name().map(OsStr::to_os_string).unwrap_or_default();
+            return Some(Ok(RawEntry {
+                parent: Rc::new(parent),
+                name,
+                depth: 0,
+            }));
+        }
+        if let Err(e) = self.push(root, 0) {
+            return Some(Err(WalkError::Io(e)));
+        }
+        None
+    }
+
+    fn push(&mut self, dir: PathBuf, depth: usize) -> std::io::Result<()> {
+        let read_dir = fs::read_dir(&dir)?;
+        if self.detect_loops
+            && let Ok(md) = fs::metadata(&dir)
+            && let Some(id) = platform::file_id(&dir, &md)
+        {
+            self.visited.insert(id);
+        }
+        self.stack.push(RawFrame {
+            read_dir,
+            dir: Rc::new(dir),
+            depth,
+        });
+        Ok(())
+    }
+}
+
+impl Iterator for RawWalk {
+    type Item = Result<RawEntry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(top) = self.stack.last_mut() else {
+                let root = self.roots.next()?;
+                if let Some(item) = self.start(root) {
+                    return Some(item);
+                }
+                continue;
+            };
+
+            let dirent = match top.read_dir.next() {
+                Some(Ok(dirent)) => dirent,
+                Some(Err(e)) => return Some(Err(WalkError::Io(e))),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            let entry = RawEntry {
+                parent: Rc::clone(&top.dir),
+                name: dirent.file_name(),
+                depth: top.depth + 1,
+            };
+
+            let file_type = match dirent.file_type() {
+                Ok(ft) => ft,
+                Err(e) => return Some(Err(WalkError::Io(e))),
+            };
+            let is_dir = if file_type.is_symlink() && self.opts.follow_links {
+                match fs::metadata(entry.to_path_buf()) {
+                    Ok(md) => md.is_dir(),
+                    Err(e) => return Some(Err(WalkError::Io(e))),
+                }
+            } else {
+                file_type.is_dir()
+            };
+
+            if is_dir && entry.depth <= self.opts.max_depth {
+                let path = entry.to_path_buf();
+                if self.opts.follow_links
+                    && self.detect_loops
+                    && let Ok(md) = fs::metadata(&path)
+                    && let Some(id) = platform::file_id(&path, &md)
+                    && self.visited.contains(&id)
+                {
+                    continue;
+                }
+                if let Err(e) = self.push(path, entry.depth) {
+                    return Some(Err(WalkError::Io(e)));
+                }
+            }
+
+            return Some(Ok(entry));
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::cmp::Ordering;
+use std::fs::{self, DirEntry};
+use std::io;
+use std::time::SystemTime;
+
+use crate::Entry;
+
+pub(crate) type MetaCmp = Box<dyn FnMut(&Entry, &fs::Metadata, &Entry, &fs::Metadata) -> Ordering>;
+
+pub(crate) struct Child {
+    pub(crate) dirent: io::Result<DirEntry>,
+    pub(crate) md: Option<fs::Metadata>,
+}
+
+impl Child {
+    pub(crate) fn new(dirent: io::Result<DirEntry>) -> Self {
+        Self { dirent, md: None }
+    }
+
+    fn stat(&mut self) -> Option<&fs::Metadata> {
+        if self.md.is_none() {
+            self.md = self.dirent.as_ref().ok().and_then(|d| d.metadata().ok());
+        }
+        self.md.as_ref()
+    }
+}
+
+pub(crate) enum Sort {
+    Mtime { descending: bool },
+    Meta(MetaCmp),
+    Shuffle { seed: u64, state: u64 },
+    Natural,
+    RecentDirs,
+}
+
+impl Sort {
+    pub(crate) fn reset(&mut self) {
+        if let Sort::Shuffle { seed, state } = self {
+            *state = *seed;
+        }
+    }
+
+    pub(crate) fn apply(&mut self, mut children: Vec<Child>, depth: usize) -> Vec<Child> {
+        match self {
+            Sort::Mtime { descending } => {
+                let descending = *descending;
+                let mut keyed: Vec<(Option<SystemTime>, Child)> = children
+                    .into_iter()
+                    .map(|mut c| (c.stat().and_then(|m| m.modified().ok()), c))
+                    .collect();
+                keyed.sort_by(|(a_time, a), (b_time, b)| {
+                    let by_time = match (a_time, b_time) {
+                        (Some(x), Some(y)) if descending => y.cmp(x),
+                        (Some(x), Some(y)) => x.cmp(y),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    };
+                    by_time.then_with(|| by_name(a, b))
+                });
+                keyed.into_iter().map(|(_, c)| c).collect()
+            }
+            Sort::Meta(cmp) => {
+                let mut keyed: Vec<(Option<Entry>, Child)> = children
+                    .drain(..)
+                    .map(|mut c| {
+                        c.stat();
+                        let entry = c.dirent.as_ref().ok().map(|d| Entry::new(d.path(), depth));
+                        (entry, c)
+                    })
+                    .collect();
+                keyed.sort_by(|(a_entry, a), (b_entry, b)| {
+                    match (a_entry.as_ref().zip(a.md.as_ref()), b_entry.as_ref().zip(b.md.as_ref())) {
+                        (Some((ae, am)), Some((be, bm))) => cmp(ae, am, be, bm),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => by_name(a, b),
+                    }
+                });
+                keyed.into_iter().map(|(_, c)| c).collect()
+            }
+            Sort::Natural => {
+                children.sort_by(|a, b| match (&a.dirent, &b.dirent) {
+                    (Ok(x), Ok(y)) => natural_cmp(
+                        &x.file_name().to_string_lossy(),
+                        &y.file_name().to_string_lossy(),
+                    )
+                    .then_with(|| by_name(a, b)),
+                    _ => by_name(a, b),
+                });
+                children
+            }
+            // Subdirectories come first, most recently modified leading; files follow by name.
+            Sort::RecentDirs => {
+                let mut keyed: Vec<(Option<SystemTime>, Child)> = children
+                    .into_iter()
+                    .map(|mut c| {
+                        let mtime = c.stat().filter(|m| m.is_dir()).map(|m| m.modified().ok());
+                        (mtime.map(|t| t.unwrap_or(SystemTime::UNIX_EPOCH)), c)
+                    })
+                    .collect();
+                keyed.sort_by(|(a_time, a), (b_time, b)| {
+                    let by_time = match (a_time, b_time) {
+                        (Some(x), Some(y)) => y.cmp(x),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    };
+                    by_time.then_with(|| by_name(a, b))
+                });
+                keyed.into_iter().map(|(_, c)| c).collect()
+            }
+            Sort::Shuffle { state, .. } => {
+                // Start from name order so the result doesn't depend on `read_dir` order.
+                children.sort_by(by_name);
+                for i in (1..children.len()).rev() {
+                    let j = (xorshift(state) % (i as u64 + 1)) as usize;
+                    children.swap(i, j);
+                }
+                children
+            }
+        }
+    }
+}
+
+fn xorshift(state: &mut u64) -> u64 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    *state = x;
+    x
+}
+
+fn by_name(a: &Child, b: &Child) -> Ordering {
+    match (&a.dirent, &b.dirent) {
+        (Ok(a), Ok(b)) => a.file_name().cmp(&b.file_name()),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => Ordering::Equal,
+    }
+}
+
+// Digit runs compare by value, so `file2` sorts before `file10`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_run = digit_run(&mut a);
+                let y_run = digit_run(&mut b);
+                let x_num = x_run.trim_start_matches('0');
+                let y_num = y_run.trim_start_matches('0');
+                let ord = x_num
+                    .len()
+                    .cmp(&y_num.len())
+                    .then_with(|| x_num.cmp(y_num))
+                    .then_with(|| x_run.len().cmp(&y_run.len()));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn digit_run(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut run = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        run.push(c);
+    }
+    run
+}
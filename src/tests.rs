@@ -1,9 +1,15 @@
 use super::*;
 use std::fs::{self, File};
 use std::io::Write;
-use std::os::unix::fs::symlink;
-use std::path::PathBuf;
+use std::os::unix::fs::{symlink, DirEntryExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::env;
+use std::ffi::OsString;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
 
 fn create_temp_dir(name: &str) -> PathBuf {
     let tmp = env::temp_dir().join(name);
@@ -141,13 +147,12 @@ fn walkdir_ignores_broken_symlinks() {
                     found_valid_links += 1;
                 }
             }
-            Err(err) => match err {
-                WalkError::Io(io_err) => {
+            Err(err) => {
+                if let WalkError::Io(io_err) = err {
                     println!("IO error: {:?}", io_err);
                     found_broken_links += 1;
                 }
-                _ => {}
-            },
+            }
         }
     }
 
@@ -185,3 +190,2862 @@ fn walkdir_follow_symlinks_no_loop_detection() {
 
     assert!(count > 2, "Expected to visit multiple paths when following symbolic links");
 }
+
+#[test]
+fn walkdir_entry_display_helpers() {
+    println!("\nEntry display helpers:");
+
+    let tmp = create_temp_dir("walkdir_minimal_display");
+    File::create(tmp.join("file.txt")).unwrap();
+
+    for entry in WalkDir::new(&tmp).unwrap() {
+        let e = entry.unwrap();
+        println!("{}", e.display());
+        assert_eq!(e.display().to_string(), e.path().display().to_string());
+        assert_eq!(e.as_os_str(), e.path().as_os_str());
+    }
+}
+
+#[test]
+fn walkdir_sort_by_mtime() {
+    println!("\nSort by mtime:");
+
+    let tmp = create_temp_dir("walkdir_minimal_sort_mtime");
+    let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    for (i, name) in ["b.txt", "c.txt", "a.txt"].iter().enumerate() {
+        let f = File::create(tmp.join(name)).unwrap();
+        f.set_modified(base + Duration::from_secs(i as u64 * 60)).unwrap();
+    }
+
+    let names = |descending| -> Vec<String> {
+        WalkDir::new(&tmp).unwrap()
+            .sort_by_mtime(descending)
+            .map(|e| e.unwrap().path().file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    };
+
+    let ascending = names(false);
+    println!("{:?}", ascending);
+    assert_eq!(ascending, vec!["b.txt", "c.txt", "a.txt"]);
+    assert_eq!(names(true), vec!["a.txt", "c.txt", "b.txt"]);
+}
+
+#[test]
+fn walkdir_byte_budget() {
+    println!("\nByte budget:");
+
+    let tmp = create_temp_dir("walkdir_minimal_byte_budget");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    for i in 0..10 {
+        fs::write(tmp.join(format!("file_{i}.bin")), [0u8; 100]).unwrap();
+    }
+
+    let mut files = 0;
+    for entry in WalkDir::new(&tmp).unwrap().byte_budget(350) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        if e.metadata().unwrap().is_file() {
+            files += 1;
+        }
+    }
+
+    assert_eq!(files, 3);
+}
+
+#[test]
+fn walkdir_unsupported_root() {
+    println!("\nUnsupported root:");
+
+    let tmp = create_temp_dir("walkdir_minimal_fifo_root");
+    let fifo = tmp.join("fifo");
+    let status = Command::new("mkfifo").arg(&fifo).status();
+    if !matches!(status, Ok(s) if s.success()) {
+        println!("mkfifo unavailable, skipping");
+        return;
+    }
+
+    let mut walker = WalkDir::new(&fifo).unwrap();
+    match walker.next() {
+        Some(Err(WalkError::UnsupportedRoot(p, ft))) => {
+            println!("{}", WalkError::UnsupportedRoot(p.clone(), ft));
+            assert_eq!(p, fifo);
+            assert!(!ft.is_dir() && !ft.is_file());
+        }
+        other => panic!("expected UnsupportedRoot, got {:?}", other),
+    }
+    assert!(walker.next().is_none());
+}
+
+#[test]
+fn walkdir_descend_if() {
+    println!("\nDescend if:");
+
+    let tmp = create_temp_dir("walkdir_minimal_descend_if");
+    fs::create_dir_all(tmp.join("src_main/nested")).unwrap();
+    fs::create_dir_all(tmp.join("docs")).unwrap();
+    File::create(tmp.join("top.txt")).unwrap();
+    File::create(tmp.join("src_main/lib.rs")).unwrap();
+    File::create(tmp.join("docs/guide.md")).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap().descend_if(|p, _| {
+        p.file_name().map(|n| n.to_string_lossy().starts_with("src")).unwrap_or(false)
+    });
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.iter().any(|p| p.ends_with("top.txt")));
+    assert!(paths.iter().any(|p| p.ends_with("src_main/lib.rs")));
+    assert!(paths.iter().any(|p| p.ends_with("src_main/nested")));
+    assert!(paths.iter().any(|p| p.ends_with("docs")));
+    assert!(!paths.iter().any(|p| p.ends_with("guide.md")));
+}
+
+#[test]
+fn walkdir_crosses_mount() {
+    println!("\nCrosses mount:");
+
+    let tmp = create_temp_dir("walkdir_minimal_crosses_mount");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    File::create(tmp.join("sub/file.txt")).unwrap();
+
+    for entry in WalkDir::new(&tmp).unwrap() {
+        assert_eq!(entry.unwrap().crosses_mount(), None);
+    }
+
+    for entry in WalkDir::new(&tmp).unwrap().track_root_device(true) {
+        let e = entry.unwrap();
+        println!("{} crosses: {:?}", e.path().display(), e.crosses_mount());
+        assert_eq!(e.crosses_mount(), Some(false));
+    }
+
+    let root_dev = fs::metadata(&tmp).unwrap().dev();
+    let foreign = Entry::new(tmp.join("sub/file.txt"), 2).with_root_dev(Some(root_dev.wrapping_add(1)));
+    assert_eq!(foreign.crosses_mount(), Some(true));
+}
+
+#[test]
+fn walkdir_dir_becomes_file() {
+    println!("\nDirectory becomes file mid-walk:");
+
+    let tmp = create_temp_dir("walkdir_minimal_dir_race");
+    fs::create_dir_all(tmp.join("racy")).unwrap();
+    File::create(tmp.join("racy/child.txt")).unwrap();
+
+    let warnings = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&warnings);
+
+    let walker = WalkDir::new(&tmp).unwrap()
+        .descend_if(|p, _| {
+            fs::remove_dir_all(p).unwrap();
+            fs::write(p, "now a file").unwrap();
+            true
+        })
+        .on_error(move |err| {
+            println!("warning: {}", err);
+            counter.set(counter.get() + 1);
+        });
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert_eq!(paths, vec![tmp.join("racy")]);
+    assert_eq!(warnings.get(), 1);
+}
+
+#[test]
+fn walkdir_large_nested_tree_unchanged() {
+    println!("\nLarge nested tree:");
+
+    let tmp = create_temp_dir("walkdir_minimal_large_nested");
+    let mut expected = HashSet::new();
+    for d in 0..10 {
+        let dir = tmp.join(format!("dir_{d}"));
+        fs::create_dir_all(&dir).unwrap();
+        expected.insert((dir.clone(), 1));
+        for f in 0..20 {
+            let file = dir.join(format!("file_{f}.txt"));
+            File::create(&file).unwrap();
+            expected.insert((file, 2));
+        }
+    }
+
+    let found: HashSet<(PathBuf, usize)> = WalkDir::new(&tmp).unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            (e.path().to_path_buf(), e.depth())
+        })
+        .collect();
+
+    println!("Found {} entries", found.len());
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn walkdir_leaves_only() {
+    println!("\nLeaves only:");
+
+    let tmp = create_temp_dir("walkdir_minimal_leaves");
+    fs::create_dir_all(tmp.join("full/inner")).unwrap();
+    fs::create_dir_all(tmp.join("empty")).unwrap();
+    File::create(tmp.join("full/a.txt")).unwrap();
+    File::create(tmp.join("top.txt")).unwrap();
+
+    let leaves: Vec<PathBuf> = WalkDir::new(&tmp).unwrap()
+        .leaves_only()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path().to_path_buf())
+        .collect();
+
+    for p in &leaves {
+        println!("{}", p.display());
+    }
+
+    assert!(leaves.contains(&tmp.join("empty")));
+    assert!(leaves.contains(&tmp.join("full/inner")));
+    assert!(leaves.contains(&tmp.join("full/a.txt")));
+    assert!(leaves.contains(&tmp.join("top.txt")));
+    assert!(!leaves.contains(&tmp.join("full")));
+    assert_eq!(leaves.len(), 4);
+
+    // Directories cut off by the depth limit are judged by their contents.
+    let mut shallow: Vec<PathBuf> = WalkDir::new(&tmp)
+        .unwrap()
+        .max_depth(0)
+        .leaves_only()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    shallow.sort();
+    assert_eq!(shallow, vec![tmp.join("empty"), tmp.join("top.txt")]);
+}
+
+#[test]
+fn walkdir_multi_root_index() {
+    println!("\nMulti root index:");
+
+    let tmp = create_temp_dir("walkdir_minimal_multi_root");
+    fs::create_dir_all(tmp.join("first/sub")).unwrap();
+    fs::create_dir_all(tmp.join("second")).unwrap();
+    File::create(tmp.join("first/sub/a.txt")).unwrap();
+    File::create(tmp.join("second/b.txt")).unwrap();
+
+    let walker = WalkDir::new_multi([tmp.join("first"), tmp.join("second")]).unwrap();
+
+    let mut seen = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("[{}] {} (depth {})", e.root_index(), e.path().display(), e.depth());
+        seen.push((e.path().to_path_buf(), e.root_index(), e.depth()));
+    }
+
+    assert!(seen.contains(&(tmp.join("first/sub"), 0, 1)));
+    assert!(seen.contains(&(tmp.join("first/sub/a.txt"), 0, 2)));
+    assert!(seen.contains(&(tmp.join("second/b.txt"), 1, 1)));
+    assert_eq!(seen.len(), 3);
+
+    for entry in WalkDir::new(tmp.join("second")).unwrap() {
+        assert_eq!(entry.unwrap().root_index(), 0);
+    }
+}
+
+#[test]
+fn walkdir_exclude_globs() {
+    println!("\nExclude globs:");
+
+    let tmp = create_temp_dir("walkdir_minimal_exclude_globs");
+    fs::create_dir_all(tmp.join("src/cache/deep")).unwrap();
+    File::create(tmp.join("keep.txt")).unwrap();
+    File::create(tmp.join("scratch.tmp")).unwrap();
+    File::create(tmp.join("src/lib.rs")).unwrap();
+    File::create(tmp.join("src/old.tmp")).unwrap();
+    File::create(tmp.join("src/cache/blob.bin")).unwrap();
+    File::create(tmp.join("src/cache/deep/more.bin")).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap()
+        .exclude_globs(["*.tmp".to_string(), "**/cache/**".to_string()]);
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("keep.txt")));
+    assert!(paths.contains(&tmp.join("src/lib.rs")));
+    assert!(!paths.iter().any(|p| p.extension().is_some_and(|x| x == "tmp")));
+    assert!(!paths.iter().any(|p| p.starts_with(tmp.join("src/cache/"))
+        && p != &tmp.join("src/cache")));
+}
+
+#[test]
+fn walkdir_builder_getters() {
+    println!("\nBuilder getters:");
+
+    let tmp = create_temp_dir("walkdir_minimal_getters");
+
+    let walker = WalkDir::new(&tmp).unwrap();
+    assert!(!walker.is_following_links());
+    assert!(walker.is_detecting_loops());
+
+    let walker = walker.follow_links(true).detect_loops(false);
+    assert!(walker.is_following_links());
+    assert!(!walker.is_detecting_loops());
+}
+
+#[test]
+fn walkdir_follow_dir_links_only() {
+    println!("\nFollow dir links only:");
+
+    let tmp = create_temp_dir("walkdir_minimal_dir_links_only");
+    fs::create_dir_all(tmp.join("real/target_dir")).unwrap();
+    File::create(tmp.join("real/target_dir/inside.txt")).unwrap();
+    fs::write(tmp.join("real/target.txt"), "data").unwrap();
+
+    fs::create_dir_all(tmp.join("walk")).unwrap();
+    symlink(tmp.join("real/target_dir"), tmp.join("walk/dir_link")).unwrap();
+    symlink(tmp.join("real/target.txt"), tmp.join("walk/file_link")).unwrap();
+    symlink("/nonexistent/path", tmp.join("walk/broken_link")).unwrap();
+
+    let walker = WalkDir::new(tmp.join("walk")).unwrap().follow_dir_links_only(true);
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("walk/dir_link/inside.txt")));
+    assert!(paths.contains(&tmp.join("walk/file_link")));
+    assert!(paths.contains(&tmp.join("walk/broken_link")));
+    assert_eq!(paths.len(), 4);
+}
+
+#[test]
+fn walkdir_strict_stops_on_first_error() {
+    println!("\nStrict mode:");
+
+    let tmp = create_temp_dir("walkdir_minimal_strict");
+    for i in 0..3 {
+        symlink("/nonexistent/path", tmp.join(format!("broken_{i}"))).unwrap();
+    }
+
+    let lenient_errors = WalkDir::new(&tmp).unwrap()
+        .follow_links(true)
+        .filter(|e| e.is_err())
+        .count();
+    assert_eq!(lenient_errors, 3);
+
+    let mut walker = WalkDir::new(&tmp).unwrap().follow_links(true).strict(true);
+    let mut errors = 0;
+    for item in walker.by_ref() {
+        if let Err(err) = item {
+            println!("error: {}", err);
+            errors += 1;
+        }
+    }
+
+    assert_eq!(errors, 1);
+    assert!(walker.next().is_none());
+}
+
+#[test]
+fn walkdir_metadata_map() {
+    println!("\nMetadata map:");
+
+    let tmp = create_temp_dir("walkdir_minimal_metadata_map");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    fs::write(tmp.join("a.txt"), "12345").unwrap();
+    fs::write(tmp.join("sub/b.txt"), "1234567890").unwrap();
+
+    let map = WalkDir::new(&tmp).unwrap().metadata_map().unwrap();
+    let paths: HashSet<PathBuf> = WalkDir::new(&tmp).unwrap()
+        .map(|e| e.unwrap().path().to_path_buf())
+        .collect();
+
+    for (p, md) in &map {
+        println!("{} -> {} bytes", p.display(), md.len());
+    }
+
+    assert_eq!(map.keys().cloned().collect::<HashSet<_>>(), paths);
+    assert_eq!(map[&tmp.join("a.txt")].len(), 5);
+    assert_eq!(map[&tmp.join("sub/b.txt")].len(), 10);
+    assert!(map[&tmp.join("sub")].is_dir());
+}
+
+#[test]
+fn walkdir_lowercase_names() {
+    println!("\nLowercase names:");
+
+    let tmp = create_temp_dir("walkdir_minimal_lowercase");
+    fs::create_dir_all(tmp.join("MixedDir")).unwrap();
+    fs::write(tmp.join("MixedDir/README.TXT"), "hello").unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap().lowercase_names(true);
+
+    let mut entries = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{} (real: {})", e.path().display(), e.real_path().display());
+        entries.push(e);
+    }
+
+    let names: Vec<String> = entries
+        .iter()
+        .map(|e| e.path().file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert!(names.contains(&"mixeddir".to_string()));
+    assert!(names.contains(&"readme.txt".to_string()));
+
+    let file = entries.iter().find(|e| e.path().ends_with("readme.txt")).unwrap();
+    assert_eq!(file.path(), tmp.join("mixeddir/readme.txt"));
+    assert_eq!(file.real_path(), tmp.join("MixedDir/README.TXT"));
+    assert_eq!(file.metadata().unwrap().len(), 5);
+
+    let leaves: Vec<PathBuf> = WalkDir::new(&tmp)
+        .unwrap()
+        .lowercase_names(true)
+        .leaves_only()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    assert_eq!(leaves, vec![tmp.join("mixeddir/readme.txt")]);
+}
+
+#[test]
+fn walkdir_gitignore_basic() {
+    println!("\nGitignore basic:");
+
+    let tmp = create_temp_dir("walkdir_minimal_gitignore_basic");
+    fs::create_dir_all(tmp.join("target/debug")).unwrap();
+    fs::create_dir_all(tmp.join("src/build")).unwrap();
+    fs::write(tmp.join(".gitignore"), "# build output\ntarget/\n*.log\n/build\n").unwrap();
+    File::create(tmp.join("target/debug/app")).unwrap();
+    File::create(tmp.join("src/main.rs")).unwrap();
+    File::create(tmp.join("src/trace.log")).unwrap();
+    File::create(tmp.join("src/build/keep.rs")).unwrap();
+    File::create(tmp.join("build")).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap().respect_gitignore(true);
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("src/main.rs")));
+    assert!(paths.contains(&tmp.join("src/build/keep.rs")));
+    assert!(!paths.iter().any(|p| p.starts_with(tmp.join("target"))));
+    assert!(!paths.contains(&tmp.join("src/trace.log")));
+    assert!(!paths.contains(&tmp.join("build")));
+}
+
+#[test]
+fn walkdir_gitignore_negation() {
+    println!("\nGitignore negation:");
+
+    let tmp = create_temp_dir("walkdir_minimal_gitignore_negation");
+    fs::write(tmp.join(".gitignore"), "*.txt\n!important.txt\n").unwrap();
+    File::create(tmp.join("notes.txt")).unwrap();
+    File::create(tmp.join("important.txt")).unwrap();
+    File::create(tmp.join("code.rs")).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap().respect_gitignore(true);
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("important.txt")));
+    assert!(paths.contains(&tmp.join("code.rs")));
+    assert!(!paths.contains(&tmp.join("notes.txt")));
+}
+
+#[test]
+fn walkdir_gitignore_nested() {
+    println!("\nGitignore nested:");
+
+    let tmp = create_temp_dir("walkdir_minimal_gitignore_nested");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    fs::create_dir_all(tmp.join("c")).unwrap();
+    fs::write(tmp.join(".gitignore"), "*.tmp\n").unwrap();
+    fs::write(tmp.join("a/.gitignore"), "*.bak\n!keep.tmp\n").unwrap();
+    File::create(tmp.join("top.bak")).unwrap();
+    File::create(tmp.join("a/b/deep.bak")).unwrap();
+    File::create(tmp.join("a/b/keep.tmp")).unwrap();
+    File::create(tmp.join("a/b/drop.tmp")).unwrap();
+    File::create(tmp.join("c/other.bak")).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap().respect_gitignore(true);
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("top.bak")));
+    assert!(paths.contains(&tmp.join("c/other.bak")));
+    assert!(paths.contains(&tmp.join("a/b/keep.tmp")));
+    assert!(!paths.contains(&tmp.join("a/b/deep.bak")));
+    assert!(!paths.contains(&tmp.join("a/b/drop.tmp")));
+}
+
+#[test]
+fn walkdir_sized_tree() {
+    println!("\nSized tree:");
+
+    let tmp = create_temp_dir("walkdir_minimal_sized_tree");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    fs::create_dir_all(tmp.join("c")).unwrap();
+    fs::write(tmp.join("top.bin"), [0u8; 10]).unwrap();
+    fs::write(tmp.join("a/one.bin"), [0u8; 100]).unwrap();
+    fs::write(tmp.join("a/b/two.bin"), [0u8; 1000]).unwrap();
+    fs::write(tmp.join("c/three.bin"), [0u8; 7]).unwrap();
+
+    let tree = WalkDir::new(&tmp).unwrap().sized_tree().unwrap();
+
+    fn find<'a>(node: &'a SizedNode, path: &Path) -> Option<&'a SizedNode> {
+        if node.path == path {
+            return Some(node);
+        }
+        node.children.iter().find_map(|c| find(c, path))
+    }
+
+    let size_of = |p: &str| find(&tree, &tmp.join(p)).unwrap().size;
+    println!("root: {}, a: {}, a/b: {}, c: {}", tree.size, size_of("a"), size_of("a/b"), size_of("c"));
+
+    assert_eq!(tree.path, tmp);
+    assert_eq!(tree.size, 1117);
+    assert_eq!(size_of("a"), 1100);
+    assert_eq!(size_of("a/b"), 1000);
+    assert_eq!(size_of("c"), 7);
+    assert_eq!(tree.children.len(), 3);
+
+    let flat = create_temp_dir("walkdir_minimal_sized_tree_transparent");
+    fs::create_dir_all(flat.join("__t__")).unwrap();
+    fs::write(flat.join("__t__/f.bin"), [0u8; 100]).unwrap();
+    let tree = WalkDir::new(&flat)
+        .unwrap()
+        .transparent_dirs(["__t__".to_string()])
+        .sized_tree()
+        .unwrap();
+    assert_eq!(tree.size, 100);
+    assert_eq!(tree.children.len(), 1);
+    assert_eq!(find(&tree, &flat.join("__t__")).unwrap().size, 100);
+}
+
+#[test]
+fn walkdir_dedupe_overlapping_roots() {
+    println!("\nDedupe overlapping roots:");
+
+    let tmp = create_temp_dir("walkdir_minimal_dedupe");
+    fs::create_dir_all(tmp.join("outer/inner")).unwrap();
+    File::create(tmp.join("outer/inner/shared.txt")).unwrap();
+    File::create(tmp.join("outer/own.txt")).unwrap();
+    symlink(tmp.join("outer"), tmp.join("alias")).unwrap();
+
+    let roots = [tmp.join("outer"), tmp.join("alias/inner")];
+
+    let duplicated = WalkDir::new_multi(&roots).unwrap()
+        .filter(|e| e.as_ref().unwrap().path().ends_with("shared.txt"))
+        .count();
+    assert_eq!(duplicated, 2);
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new_multi(&roots).unwrap().dedupe(true) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert_eq!(paths.iter().filter(|p| p.ends_with("shared.txt")).count(), 1);
+    assert!(paths.iter().any(|p| p.ends_with("own.txt")));
+}
+
+#[test]
+fn walkdir_yielded_count() {
+    println!("\nYielded count:");
+
+    let tmp = create_temp_dir("walkdir_minimal_yielded_count");
+    for i in 0..5 {
+        File::create(tmp.join(format!("file_{i}.txt"))).unwrap();
+    }
+    symlink("/nonexistent/path", tmp.join("broken")).unwrap();
+
+    let mut walker = WalkDir::new(&tmp).unwrap().follow_links(true);
+    assert_eq!(walker.yielded_count(), 0);
+
+    let mut last = 0;
+    let mut errors = 0;
+    while let Some(item) = walker.next() {
+        if item.is_err() {
+            errors += 1;
+        }
+        println!("yielded so far: {}", walker.yielded_count());
+        assert!(walker.yielded_count() >= last);
+        last = walker.yielded_count();
+    }
+
+    assert_eq!(errors, 1);
+    assert_eq!(walker.yielded_count(), 5);
+}
+
+#[test]
+fn walkdir_owned_by() {
+    println!("\nOwned by:");
+
+    let tmp = create_temp_dir("walkdir_minimal_owned_by");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    File::create(tmp.join("a.txt")).unwrap();
+    File::create(tmp.join("sub/b.txt")).unwrap();
+
+    let md = fs::metadata(tmp.join("a.txt")).unwrap();
+    let (uid, gid) = (md.uid(), md.gid());
+
+    let mine = WalkDir::new(&tmp).unwrap().owned_by(uid).group(gid)
+        .map(|e| e.unwrap().path().to_path_buf())
+        .collect::<Vec<_>>();
+    println!("{:?}", mine);
+    assert_eq!(mine.len(), 3);
+
+    let others = WalkDir::new(&tmp).unwrap().owned_by(uid.wrapping_add(1)).count();
+    assert_eq!(others, 0);
+
+    let other_group = WalkDir::new(&tmp).unwrap().owned_by(uid).group(gid.wrapping_add(1)).count();
+    assert_eq!(other_group, 0);
+}
+
+#[test]
+fn walkdir_yield_dir_before_open() {
+    println!("\nYield dir before open:");
+
+    let tmp = create_temp_dir("walkdir_minimal_dir_before_open");
+    fs::create_dir_all(tmp.join("vanishing")).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap()
+        .yield_dir_before_open(true)
+        .descend_if(|p, _| {
+            let _ = fs::remove_dir(p);
+            true
+        });
+
+    let items: Vec<_> = walker.collect();
+    for item in &items {
+        println!("{:?}", item.as_ref().map(|e| e.path().to_path_buf()));
+    }
+
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].as_ref().unwrap().path(), tmp.join("vanishing"));
+    assert!(matches!(items[1], Err(WalkError::Io(_))));
+}
+
+#[test]
+fn walkdir_raw_walk_matches() {
+    println!("\nRaw walk:");
+
+    let tmp = create_temp_dir("walkdir_minimal_raw_walk");
+    fs::create_dir_all(tmp.join("a/b/c")).unwrap();
+    fs::create_dir_all(tmp.join("d")).unwrap();
+    File::create(tmp.join("a/one.txt")).unwrap();
+    File::create(tmp.join("a/b/c/two.txt")).unwrap();
+    File::create(tmp.join("d/three.txt")).unwrap();
+
+    let normal: HashSet<(PathBuf, usize)> = WalkDir::new(&tmp).unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            (e.path().to_path_buf(), e.depth())
+        })
+        .collect();
+
+    let raw: HashSet<(PathBuf, usize)> = WalkDir::new(&tmp).unwrap()
+        .raw_walk()
+        .map(|e| {
+            let e = e.unwrap().to_entry();
+            println!("{} ({})", e.path().display(), e.depth());
+            (e.path().to_path_buf(), e.depth())
+        })
+        .collect();
+
+    assert_eq!(raw, normal);
+}
+
+#[test]
+fn walkdir_mark_truncated() {
+    println!("\nMark truncated:");
+
+    let tmp = create_temp_dir("walkdir_minimal_truncated");
+    fs::create_dir_all(tmp.join("a/deeper")).unwrap();
+    fs::create_dir_all(tmp.join("b")).unwrap();
+    File::create(tmp.join("top.txt")).unwrap();
+    File::create(tmp.join("a/hidden.txt")).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap().max_depth(0).mark_truncated(true);
+
+    let mut entries = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{} truncated: {}", e.path().display(), e.is_truncated());
+        entries.push(e);
+    }
+
+    assert!(entries.iter().all(|e| e.depth() == 1));
+    for e in &entries {
+        assert_eq!(e.is_truncated(), e.path().is_dir(), "{}", e.path().display());
+    }
+    assert_eq!(entries.len(), 3);
+
+    let unmarked = WalkDir::new(&tmp).unwrap().max_depth(0)
+        .filter(|e| e.as_ref().unwrap().is_truncated())
+        .count();
+    assert_eq!(unmarked, 0);
+}
+
+#[test]
+fn walkdir_sort_by_meta_size() {
+    println!("\nSort by metadata (size):");
+
+    let tmp = create_temp_dir("walkdir_minimal_sort_meta");
+    fs::write(tmp.join("small.bin"), [0u8; 10]).unwrap();
+    fs::write(tmp.join("large.bin"), [0u8; 1000]).unwrap();
+    fs::write(tmp.join("medium.bin"), [0u8; 100]).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap()
+        .sort_by_meta(|_, a, _, b| b.len().cmp(&a.len()));
+
+    let mut sizes = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        let size = e.metadata().unwrap().len();
+        println!("{} ({} bytes)", e.path().display(), size);
+        sizes.push(size);
+    }
+
+    assert_eq!(sizes, vec![1000, 100, 10]);
+}
+
+#[test]
+fn walkdir_collect_rev() {
+    println!("\nCollect reversed:");
+
+    let tmp = create_temp_dir("walkdir_minimal_collect_rev");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    File::create(tmp.join("a/one.txt")).unwrap();
+    File::create(tmp.join("a/b/two.txt")).unwrap();
+
+    let forward: Vec<PathBuf> = WalkDir::new(&tmp).unwrap()
+        .sort_by_mtime(false)
+        .map(|e| e.unwrap().path().to_path_buf())
+        .collect();
+    let reversed: Vec<PathBuf> = WalkDir::new(&tmp).unwrap()
+        .sort_by_mtime(false)
+        .collect_rev()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path().to_path_buf())
+        .collect();
+
+    println!("{:?}", reversed);
+    assert_eq!(reversed, forward.into_iter().rev().collect::<Vec<_>>());
+}
+
+#[test]
+fn walkdir_max_file_size() {
+    println!("\nMax file size:");
+
+    let tmp = create_temp_dir("walkdir_minimal_max_file_size");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    fs::write(tmp.join("small.bin"), [0u8; 10]).unwrap();
+    fs::write(tmp.join("exact.bin"), [0u8; 100]).unwrap();
+    fs::write(tmp.join("large.bin"), [0u8; 1000]).unwrap();
+    fs::write(tmp.join("sub/tiny.bin"), [0u8; 1]).unwrap();
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().max_file_size(100) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("small.bin")));
+    assert!(paths.contains(&tmp.join("exact.bin")));
+    assert!(paths.contains(&tmp.join("sub")));
+    assert!(paths.contains(&tmp.join("sub/tiny.bin")));
+    assert!(!paths.contains(&tmp.join("large.bin")));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn walkdir_write_jsonl() {
+    println!("\nWrite JSON lines:");
+
+    let tmp = create_temp_dir("walkdir_minimal_jsonl");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    File::create(tmp.join("sub/file.txt")).unwrap();
+
+    let mut out = Vec::new();
+    WalkDir::new(&tmp).unwrap().write_jsonl(&mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    print!("{}", text);
+
+    let expected: Vec<(String, u64)> = WalkDir::new(&tmp).unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            (e.path().to_string_lossy().into_owned(), e.depth() as u64)
+        })
+        .collect();
+
+    let parsed: Vec<(String, u64)> = text
+        .lines()
+        .map(|line| {
+            let v: serde_json::Value = serde_json::from_str(line).unwrap();
+            (v["path"].as_str().unwrap().to_string(), v["depth"].as_u64().unwrap())
+        })
+        .collect();
+
+    assert_eq!(parsed, expected);
+}
+
+#[test]
+fn walkdir_step_in_slices() {
+    println!("\nStep in slices:");
+
+    let tmp = create_temp_dir("walkdir_minimal_step");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    for i in 0..12 {
+        File::create(tmp.join(format!("a/file_{i}.txt"))).unwrap();
+    }
+    File::create(tmp.join("a/b/deep.txt")).unwrap();
+
+    let full: Vec<PathBuf> = WalkDir::new(&tmp).unwrap()
+        .map(|e| e.unwrap().path().to_path_buf())
+        .collect();
+
+    let mut walker = WalkDir::new(&tmp).unwrap();
+    let mut sliced = Vec::new();
+    loop {
+        let (items, done) = walker.step(5);
+        println!("slice of {} (done: {})", items.len(), done);
+        assert!(items.len() <= 5);
+        sliced.extend(items.into_iter().map(|e| e.unwrap().path().to_path_buf()));
+        if done {
+            break;
+        }
+    }
+
+    assert_eq!(sliced, full);
+}
+
+#[test]
+fn walkdir_common_ancestor() {
+    println!("\nCommon ancestor:");
+
+    let tmp = create_temp_dir("walkdir_minimal_common_ancestor");
+    fs::create_dir_all(tmp.join("src/net/tcp")).unwrap();
+    fs::create_dir_all(tmp.join("docs")).unwrap();
+    File::create(tmp.join("src/net/socket.rs")).unwrap();
+    File::create(tmp.join("src/net/tcp/stream.rs")).unwrap();
+    File::create(tmp.join("docs/readme.md")).unwrap();
+
+    let computed = WalkDir::new(&tmp).unwrap()
+        .filter_entry(|e| !e.path().ends_with("docs") && !e.path().ends_with("tcp"))
+        .common_ancestor()
+        .unwrap();
+    println!("{:?}", computed);
+    assert_eq!(computed, Some(tmp.join("src")));
+
+    let empty = create_temp_dir("walkdir_minimal_common_ancestor_empty");
+    assert_eq!(WalkDir::new(&empty).unwrap().common_ancestor().unwrap(), None);
+
+    let single = create_temp_dir("walkdir_minimal_common_ancestor_single");
+    File::create(single.join("only.txt")).unwrap();
+    assert_eq!(WalkDir::new(&single).unwrap().common_ancestor().unwrap(), Some(single.clone()));
+}
+
+#[test]
+fn walkdir_resolve_followed_paths() {
+    println!("\nResolve followed paths:");
+
+    let tmp = create_temp_dir("walkdir_minimal_resolve_followed");
+    fs::create_dir_all(tmp.join("real/inner")).unwrap();
+    File::create(tmp.join("real/inner/file.txt")).unwrap();
+    fs::create_dir_all(tmp.join("walk")).unwrap();
+    symlink(tmp.join("real"), tmp.join("walk/link")).unwrap();
+    File::create(tmp.join("walk/plain.txt")).unwrap();
+
+    let walker = WalkDir::new(tmp.join("walk")).unwrap()
+        .follow_links(true)
+        .resolve_followed_paths(true);
+
+    let mut seen = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{} (depth {})", e.path().display(), e.depth());
+        seen.push((e.path().to_path_buf(), e.depth()));
+    }
+
+    let real = fs::canonicalize(tmp.join("real")).unwrap();
+    assert!(seen.contains(&(tmp.join("walk/link"), 1)));
+    assert!(seen.contains(&(tmp.join("walk/plain.txt"), 1)));
+    assert!(seen.contains(&(real.join("inner"), 2)));
+    assert!(seen.contains(&(real.join("inner/file.txt"), 3)));
+}
+
+#[test]
+fn walkdir_max_children() {
+    println!("\nMax children:");
+
+    let tmp = create_temp_dir("walkdir_minimal_max_children");
+    fs::create_dir_all(tmp.join("big")).unwrap();
+    for i in 0..100 {
+        File::create(tmp.join(format!("big/file_{i}.txt"))).unwrap();
+    }
+
+    let mut in_big = 0;
+    for entry in WalkDir::new(&tmp).unwrap().max_children(10) {
+        let e = entry.unwrap();
+        if e.path().parent() == Some(tmp.join("big").as_path()) {
+            in_big += 1;
+        }
+    }
+
+    println!("Yielded {} children of big/", in_big);
+    assert_eq!(in_big, 10);
+}
+
+#[test]
+fn walkdir_manifest_stable() {
+    println!("\nManifest:");
+
+    let tmp = create_temp_dir("walkdir_minimal_manifest");
+    fs::create_dir_all(tmp.join("b/c")).unwrap();
+    fs::write(tmp.join("z.txt"), "zz").unwrap();
+    fs::write(tmp.join("b/a.txt"), "a").unwrap();
+    fs::write(tmp.join("b/c/d.txt"), "dddd").unwrap();
+
+    let first = WalkDir::new(&tmp).unwrap().manifest().unwrap();
+    let second = WalkDir::new(&tmp).unwrap().manifest().unwrap();
+
+    for m in &first {
+        println!("{} {} {:?}", m.rel_path.display(), m.size, m.mtime);
+    }
+
+    assert_eq!(first, second);
+    let rels: Vec<&Path> = first.iter().map(|m| m.rel_path.as_path()).collect();
+    assert_eq!(rels, vec![Path::new("b/a.txt"), Path::new("b/c/d.txt"), Path::new("z.txt")]);
+    assert_eq!(first[1].size, 4);
+
+    let multi = WalkDir::new_multi([tmp.join("b/c"), tmp.join("b")]).unwrap().manifest().unwrap();
+    let rels: Vec<&Path> = multi.iter().map(|m| m.rel_path.as_path()).collect();
+    assert_eq!(rels, vec![Path::new("a.txt"), Path::new("c/d.txt"), Path::new("d.txt")]);
+}
+
+#[test]
+fn walkdir_dir_entry_escape_hatch() {
+    println!("\nRaw DirEntry access:");
+
+    let tmp = create_temp_dir("walkdir_minimal_dir_entry");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    File::create(tmp.join("sub/file.txt")).unwrap();
+
+    for entry in WalkDir::new(&tmp).unwrap() {
+        assert!(entry.unwrap().dir_entry().is_none());
+    }
+
+    for entry in WalkDir::new(&tmp).unwrap().keep_dir_entries(true) {
+        let e = entry.unwrap();
+        let dirent = e.dir_entry().unwrap();
+        println!("{} -> {:?} (ino {})", e.path().display(), dirent.file_name(), dirent.ino());
+        assert_eq!(Some(dirent.file_name().as_os_str()), e.path().file_name());
+    }
+
+    let root_file = tmp.join("sub/file.txt");
+    let root_entry = WalkDir::new(&root_file).unwrap().keep_dir_entries(true).next().unwrap().unwrap();
+    assert!(root_entry.dir_entry().is_none());
+}
+
+#[test]
+fn walkdir_sorted_by_path() {
+    println!("\nSorted by path:");
+
+    let tmp = create_temp_dir("walkdir_minimal_sorted_by_path");
+    fs::create_dir_all(tmp.join("b/d")).unwrap();
+    fs::create_dir_all(tmp.join("a")).unwrap();
+    File::create(tmp.join("c.txt")).unwrap();
+    File::create(tmp.join("b/d/e.txt")).unwrap();
+    File::create(tmp.join("a/z.txt")).unwrap();
+
+    let sorted: Vec<PathBuf> = WalkDir::new(&tmp).unwrap()
+        .sorted_by_path()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path().strip_prefix(&tmp).unwrap().to_path_buf())
+        .collect();
+
+    println!("{:?}", sorted);
+    let expected: Vec<PathBuf> = ["a", "a/z.txt", "b", "b/d", "b/d/e.txt", "c.txt"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    assert_eq!(sorted, expected);
+}
+
+#[test]
+fn walkdir_stop_when() {
+    println!("\nStop when:");
+
+    let tmp = create_temp_dir("walkdir_minimal_stop_when");
+    fs::create_dir_all(tmp.join("src")).unwrap();
+    for name in ["a.txt", "b.rs", "c.rs", "d.txt"] {
+        File::create(tmp.join("src").join(name)).unwrap();
+    }
+
+    let walker = WalkDir::new(&tmp).unwrap()
+        .sort_by_mtime(false)
+        .stop_when(|e| e.path().extension().is_some_and(|x| x == "rs"));
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    let last = paths.last().unwrap();
+    assert!(last.extension().is_some_and(|x| x == "rs"));
+    assert_eq!(paths.iter().filter(|p| p.extension().is_some_and(|x| x == "rs")).count(), 1);
+}
+
+#[test]
+fn walkdir_error_into_io_error() {
+    println!("\nWalkError into io::Error:");
+
+    let original = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+    let round_trip: std::io::Error = WalkError::from(original).into();
+    assert_eq!(round_trip.kind(), std::io::ErrorKind::NotFound);
+    assert_eq!(round_trip.to_string(), "gone");
+
+    let looped: std::io::Error = WalkError::LoopDetected(PathBuf::from("/tmp/a/b")).into();
+    println!("{}", looped);
+    assert_eq!(looped.kind(), std::io::ErrorKind::Other);
+    assert!(looped.to_string().contains("/tmp/a/b"));
+}
+
+#[test]
+fn walkdir_max_visited_dirs() {
+    println!("\nMax visited dirs:");
+
+    let tmp = create_temp_dir("walkdir_minimal_max_visited");
+    for i in 0..5 {
+        fs::create_dir_all(tmp.join(format!("dir_{i}"))).unwrap();
+        File::create(tmp.join(format!("dir_{i}/file.txt"))).unwrap();
+    }
+
+    // The root counts as the first directory entered, in both link modes.
+    for follow in [false, true] {
+        let mut dirs = 0;
+        let mut files = 0;
+        let walker = WalkDir::new(&tmp).unwrap().follow_links(follow).max_visited_dirs(3);
+        for entry in walker {
+            let e = entry.unwrap();
+            println!("{}", e.path().display());
+            if e.path().is_dir() {
+                dirs += 1;
+            } else {
+                files += 1;
+            }
+        }
+
+        assert_eq!(dirs, 5, "follow_links({follow})");
+        assert_eq!(files, 2, "follow_links({follow})");
+    }
+
+    // Seeded ids are only for loop detection and take no slots.
+    let seeded = WalkDir::new(&tmp)
+        .unwrap()
+        .with_visited([(u64::MAX, 1), (u64::MAX, 2)])
+        .max_visited_dirs(3)
+        .filter(|e| e.as_ref().unwrap().path().is_file())
+        .count();
+    assert_eq!(seeded, 2);
+}
+
+#[test]
+fn walkdir_partition() {
+    println!("\nPartition:");
+
+    let tmp = create_temp_dir("walkdir_minimal_partition");
+    fs::create_dir_all(tmp.join("sub/inner")).unwrap();
+    File::create(tmp.join("a.txt")).unwrap();
+    File::create(tmp.join("sub/b.txt")).unwrap();
+    symlink(tmp.join("sub"), tmp.join("link")).unwrap();
+
+    let (files, dirs) = WalkDir::new(&tmp).unwrap().partition().unwrap();
+    for e in files.iter().chain(&dirs) {
+        println!("{}", e.path().display());
+    }
+
+    let files: HashSet<PathBuf> = files.iter().map(|e| e.path().to_path_buf()).collect();
+    let dirs: HashSet<PathBuf> = dirs.iter().map(|e| e.path().to_path_buf()).collect();
+    let expected_files: HashSet<PathBuf> =
+        [tmp.join("a.txt"), tmp.join("sub/b.txt"), tmp.join("link")].into();
+    let expected_dirs: HashSet<PathBuf> =
+        [tmp.clone(), tmp.join("sub"), tmp.join("sub/inner")].into();
+    assert_eq!(files, expected_files);
+    assert_eq!(dirs, expected_dirs);
+
+    let (_, dirs) = WalkDir::new(&tmp).unwrap().skip_root(true).partition().unwrap();
+    assert!(dirs.iter().all(|e| e.path() != tmp));
+    assert_eq!(dirs.len(), 2);
+
+    // The root goes through the same path transforms as the walked entries.
+    let (_, dirs) = WalkDir::new(&tmp).unwrap().posix_relative(true).partition().unwrap();
+    let dirs: HashSet<PathBuf> = dirs.iter().map(|e| e.path().to_path_buf()).collect();
+    let expected_dirs: HashSet<PathBuf> =
+        [PathBuf::from("."), PathBuf::from("sub"), PathBuf::from("sub/inner")].into();
+    assert_eq!(dirs, expected_dirs);
+}
+
+#[test]
+fn walkdir_walkignore_basic() {
+    println!("\nWalkignore basic:");
+
+    let tmp = create_temp_dir("walkdir_minimal_walkignore_basic");
+    fs::create_dir_all(tmp.join("cache/deep")).unwrap();
+    fs::create_dir_all(tmp.join("src")).unwrap();
+    fs::write(tmp.join(".walkignore"), "cache\n*.tmp\n").unwrap();
+    fs::write(tmp.join("src/.walkignore"), "gen_?.rs\n").unwrap();
+    File::create(tmp.join("cache/deep/blob")).unwrap();
+    File::create(tmp.join("src/main.rs")).unwrap();
+    File::create(tmp.join("src/gen_a.rs")).unwrap();
+    File::create(tmp.join("src/scratch.tmp")).unwrap();
+    File::create(tmp.join("gen_b.rs")).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap().respect_walkignore(true);
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("src/main.rs")));
+    assert!(!paths.iter().any(|p| p.starts_with(tmp.join("cache"))));
+    assert!(!paths.contains(&tmp.join("src/scratch.tmp")));
+    assert!(!paths.contains(&tmp.join("src/gen_a.rs")));
+    // `src/.walkignore` only applies inside `src`.
+    assert!(paths.contains(&tmp.join("gen_b.rs")));
+}
+
+#[test]
+fn walkdir_walkignore_comments() {
+    println!("\nWalkignore comments:");
+
+    let tmp = create_temp_dir("walkdir_minimal_walkignore_comments");
+    fs::write(tmp.join(".walkignore"), "# *.rs\n\n  *.bak  \n!keep.bak\n").unwrap();
+    File::create(tmp.join("lib.rs")).unwrap();
+    File::create(tmp.join("old.bak")).unwrap();
+    File::create(tmp.join("keep.bak")).unwrap();
+
+    let walker = WalkDir::new(&tmp).unwrap().respect_walkignore(true);
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("lib.rs")));
+    assert!(!paths.contains(&tmp.join("old.bak")));
+    // No negation: `!keep.bak` is a literal pattern that matches nothing here.
+    assert!(!paths.contains(&tmp.join("keep.bak")));
+}
+
+#[test]
+fn walkdir_collect_links() {
+    println!("\nCollect links:");
+
+    let tmp = create_temp_dir("walkdir_minimal_collect_links");
+    fs::create_dir_all(tmp.join("dir")).unwrap();
+    File::create(tmp.join("dir/file.txt")).unwrap();
+    symlink(tmp.join("dir"), tmp.join("dir_link")).unwrap();
+    symlink("file.txt", tmp.join("dir/rel_link")).unwrap();
+    symlink(tmp.join("missing"), tmp.join("broken")).unwrap();
+
+    let links = WalkDir::new(&tmp)
+        .unwrap()
+        .follow_links(true)
+        .collect_links()
+        .unwrap();
+    for (src, dst) in &links {
+        println!("{} -> {}", src.display(), dst.display());
+    }
+
+    let links: HashSet<(PathBuf, PathBuf)> = links.into_iter().collect();
+    let expected: HashSet<(PathBuf, PathBuf)> = [
+        (tmp.join("dir_link"), tmp.join("dir")),
+        (tmp.join("dir/rel_link"), PathBuf::from("file.txt")),
+        (tmp.join("broken"), tmp.join("missing")),
+    ]
+    .into();
+    assert_eq!(links, expected);
+
+    // The syscall uses the real path; only the reported path is lowercased.
+    symlink(tmp.join("dir"), tmp.join("dir/GOOD")).unwrap();
+    let lowered = WalkDir::new(tmp.join("dir"))
+        .unwrap()
+        .lowercase_names(true)
+        .collect_links()
+        .unwrap();
+    assert!(lowered.contains(&(tmp.join("dir/good"), tmp.join("dir"))));
+}
+
+#[test]
+fn walkdir_descent_fuel() {
+    println!("\nDescent fuel:");
+
+    let tmp = create_temp_dir("walkdir_minimal_descent_fuel");
+    for branch in ["a", "b", "c"] {
+        fs::create_dir_all(tmp.join(branch).join("x/y")).unwrap();
+        File::create(tmp.join(branch).join("x/y/file.txt")).unwrap();
+    }
+
+    let mut entered = HashSet::new();
+    for entry in WalkDir::new(&tmp).unwrap().descent_fuel(4) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        let parent = e.path().parent().unwrap().to_path_buf();
+        if parent != tmp {
+            entered.insert(parent);
+        }
+    }
+
+    assert_eq!(entered.len(), 4);
+}
+
+#[test]
+fn walkdir_entry_open() {
+    println!("\nEntry open:");
+
+    let tmp = create_temp_dir("walkdir_minimal_entry_open");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    fs::write(tmp.join("sub/data.txt"), "payload").unwrap();
+
+    let mut opened = 0;
+    for entry in WalkDir::new(&tmp).unwrap() {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        if e.path().ends_with("data.txt") {
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut e.open().unwrap(), &mut text).unwrap();
+            assert_eq!(text, "payload");
+            opened += 1;
+        } else {
+            let err = e.open().unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::IsADirectory);
+        }
+    }
+
+    assert_eq!(opened, 1);
+}
+
+#[test]
+fn walkdir_cwd_relative() {
+    println!("\nCwd relative:");
+
+    let tmp = create_temp_dir("walkdir_minimal_cwd_relative");
+    fs::create_dir_all(tmp.join("inside/deep")).unwrap();
+    File::create(tmp.join("inside/deep/file.txt")).unwrap();
+    let outside = create_temp_dir("walkdir_minimal_cwd_relative_outside");
+    File::create(outside.join("other.txt")).unwrap();
+
+    // Changing the real working directory would race with the other tests.
+    let walker = WalkDir::new_multi([&tmp, &outside]).unwrap().cwd_relative_to(tmp.join("inside"));
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&PathBuf::from(".")));
+    assert!(paths.contains(&PathBuf::from("deep")));
+    assert!(paths.contains(&PathBuf::from("deep/file.txt")));
+    assert!(paths.contains(&outside.join("other.txt")));
+}
+
+#[test]
+fn walkdir_dir_markers() {
+    println!("\nDir markers:");
+
+    let tmp = create_temp_dir("walkdir_minimal_dir_markers");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    File::create(tmp.join("a/b/one.txt")).unwrap();
+    File::create(tmp.join("a/two.txt")).unwrap();
+
+    let events: Vec<Event> = WalkDir::new(&tmp)
+        .unwrap()
+        .with_dir_markers()
+        .map(|e| e.unwrap())
+        .collect();
+
+    let mut open = vec![tmp.clone()];
+    for event in &events {
+        match event {
+            Event::Entry(e) => {
+                println!("entry {}", e.path().display());
+                assert_eq!(e.path().parent(), open.last().map(PathBuf::as_path));
+                if e.path().is_dir() {
+                    open.push(e.path().to_path_buf());
+                }
+            }
+            Event::DirEnd(p) => {
+                println!("end   {}", p.display());
+                assert_eq!(open.pop().as_ref(), Some(p));
+            }
+        }
+    }
+
+    assert!(open.is_empty());
+    assert!(matches!(events.last(), Some(Event::DirEnd(p)) if *p == tmp));
+
+    // An empty directory held back by `only_empty` still comes before its marker.
+    fs::create_dir_all(tmp.join("a/empty")).unwrap();
+    let events: Vec<Event> = WalkDir::new(&tmp)
+        .unwrap()
+        .only_empty()
+        .with_dir_markers()
+        .map(|e| e.unwrap())
+        .collect();
+    let empty = tmp.join("a/empty");
+    let entry_at = events
+        .iter()
+        .position(|e| matches!(e, Event::Entry(e) if e.path() == empty))
+        .unwrap();
+    let end_at = events
+        .iter()
+        .position(|e| matches!(e, Event::DirEnd(p) if *p == empty))
+        .unwrap();
+    assert!(entry_at < end_at);
+}
+
+#[test]
+fn walkdir_followed_link_keeps_symlink_type() {
+    println!("\nFollowed link keeps symlink type:");
+
+    let tmp = create_temp_dir("walkdir_minimal_followed_link_type");
+    let target = create_temp_dir("walkdir_minimal_followed_link_type_target");
+    File::create(target.join("file.txt")).unwrap();
+    symlink(&target, tmp.join("link")).unwrap();
+
+    let mut link_seen = false;
+    let mut through_link = 0;
+    for entry in WalkDir::new(&tmp).unwrap().follow_links(true) {
+        let e = entry.unwrap();
+        println!("{} symlink={}", e.path().display(), e.is_symlink());
+        if e.path() == tmp.join("link") {
+            link_seen = true;
+            assert!(e.is_symlink());
+            assert!(e.file_type().unwrap().is_symlink());
+            assert!(e.metadata().unwrap().is_dir());
+        } else {
+            assert!(!e.is_symlink());
+        }
+        if e.path().starts_with(tmp.join("link")) {
+            through_link += 1;
+        }
+    }
+
+    assert!(link_seen);
+    assert_eq!(through_link, 2);
+}
+
+#[test]
+fn walkdir_largest_files() {
+    println!("\nLargest files:");
+
+    let tmp = create_temp_dir("walkdir_minimal_largest_files");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    let sizes = [("a", 10), ("b", 500), ("sub/c", 40), ("sub/d", 300), ("e", 0), ("sub/f", 1000)];
+    for (name, size) in sizes {
+        fs::write(tmp.join(name), vec![0u8; size]).unwrap();
+    }
+
+    let top = WalkDir::new(&tmp).unwrap().largest_files(3).unwrap();
+    for e in &top {
+        println!("{} {}", e.path().display(), e.metadata().unwrap().len());
+    }
+
+    let paths: Vec<PathBuf> = top.iter().map(|e| e.path().to_path_buf()).collect();
+    assert_eq!(paths, vec![tmp.join("sub/f"), tmp.join("b"), tmp.join("sub/d")]);
+    assert!(WalkDir::new(&tmp).unwrap().largest_files(0).unwrap().is_empty());
+}
+
+#[test]
+fn walkdir_only_empty() {
+    println!("\nOnly empty:");
+
+    let tmp = create_temp_dir("walkdir_minimal_only_empty");
+    fs::create_dir_all(tmp.join("empty_dir")).unwrap();
+    fs::create_dir_all(tmp.join("full_dir/nested_empty")).unwrap();
+    File::create(tmp.join("empty.txt")).unwrap();
+    fs::write(tmp.join("full.txt"), "data").unwrap();
+    File::create(tmp.join("full_dir/blank.log")).unwrap();
+    fs::write(tmp.join("full_dir/notes.log"), "notes").unwrap();
+
+    let mut paths = HashSet::new();
+    for entry in WalkDir::new(&tmp).unwrap().only_empty() {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.insert(e.path().to_path_buf());
+    }
+
+    let expected: HashSet<PathBuf> = [
+        tmp.join("empty_dir"),
+        tmp.join("empty.txt"),
+        tmp.join("full_dir/nested_empty"),
+        tmp.join("full_dir/blank.log"),
+    ]
+    .into();
+    assert_eq!(paths, expected);
+
+    let shallow: Vec<Entry> = WalkDir::new(&tmp)
+        .unwrap()
+        .only_empty()
+        .max_depth(1)
+        .map(|e| e.unwrap())
+        .collect();
+    assert!(shallow.iter().any(|e| e.path() == tmp.join("empty_dir")));
+    assert!(!shallow.iter().any(|e| e.path() == tmp.join("full_dir")));
+}
+
+#[test]
+fn walkdir_shuffle_children() {
+    println!("\nShuffle children:");
+
+    let tmp = create_temp_dir("walkdir_minimal_shuffle_children");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    for i in 0..20 {
+        File::create(tmp.join(format!("file_{i:02}"))).unwrap();
+        File::create(tmp.join(format!("sub/inner_{i:02}"))).unwrap();
+    }
+
+    let order = |seed: u64| -> Vec<PathBuf> {
+        WalkDir::new(&tmp)
+            .unwrap()
+            .shuffle_children(seed)
+            .map(|e| e.unwrap().path().to_path_buf())
+            .collect()
+    };
+
+    let first = order(42);
+    for p in &first {
+        println!("{}", p.display());
+    }
+
+    assert_eq!(first.len(), 41);
+    assert_eq!(first, order(42));
+    assert_ne!(first, order(7));
+}
+
+#[test]
+fn walkdir_structure_hash() {
+    println!("\nStructure hash:");
+
+    let make = |name: &str| {
+        let tmp = create_temp_dir(name);
+        fs::create_dir_all(tmp.join("src/bin")).unwrap();
+        fs::write(tmp.join("src/lib.rs"), name).unwrap();
+        File::create(tmp.join("src/bin/main.rs")).unwrap();
+        File::create(tmp.join("README")).unwrap();
+        tmp
+    };
+    let a = make("walkdir_minimal_structure_hash_a");
+    let b = make("walkdir_minimal_structure_hash_b");
+
+    let hash = |p: &Path| WalkDir::new(p).unwrap().structure_hash().unwrap();
+    println!("{:x} {:x}", hash(&a), hash(&b));
+    assert_eq!(hash(&a), hash(&b));
+
+    // Renaming keeps the entry count but changes the shape.
+    fs::rename(b.join("README"), b.join("README.md")).unwrap();
+    assert_ne!(hash(&a), hash(&b));
+
+    fs::rename(b.join("README.md"), b.join("README")).unwrap();
+    assert_eq!(hash(&a), hash(&b));
+    fs::remove_file(b.join("README")).unwrap();
+    fs::create_dir(b.join("README")).unwrap();
+    assert_ne!(hash(&a), hash(&b));
+}
+
+#[test]
+fn walkdir_skip_unreadable_dirs() {
+    use std::os::unix::fs::PermissionsExt;
+
+    println!("\nSkip unreadable dirs:");
+
+    let tmp = create_temp_dir("walkdir_minimal_skip_unreadable");
+    fs::create_dir_all(tmp.join("locked")).unwrap();
+    File::create(tmp.join("locked/secret.txt")).unwrap();
+    File::create(tmp.join("open.txt")).unwrap();
+    fs::set_permissions(tmp.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+
+    // A privileged process can still read the directory, so there is nothing to skip.
+    if fs::read_dir(tmp.join("locked")).is_ok() {
+        fs::set_permissions(tmp.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+        println!("running privileged, skipping");
+        return;
+    }
+
+    let plain: Vec<_> = WalkDir::new(&tmp).unwrap().collect();
+    let results: Vec<_> = WalkDir::new(&tmp).unwrap().skip_unreadable_dirs(true).collect();
+    fs::set_permissions(tmp.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+    assert!(plain.iter().any(|r| r.is_err()));
+
+    let mut paths = Vec::new();
+    for result in results {
+        let e = result.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("locked")));
+    assert!(paths.contains(&tmp.join("open.txt")));
+    assert!(!paths.contains(&tmp.join("locked/secret.txt")));
+}
+
+#[test]
+fn walkdir_entry_nlink() {
+    println!("\nEntry nlink:");
+
+    let tmp = create_temp_dir("walkdir_minimal_entry_nlink");
+    File::create(tmp.join("original")).unwrap();
+    fs::hard_link(tmp.join("original"), tmp.join("alias")).unwrap();
+    File::create(tmp.join("single")).unwrap();
+
+    let mut checked = 0;
+    for entry in WalkDir::new(&tmp).unwrap().sort_by_mtime(false) {
+        let e = entry.unwrap();
+        let nlink = e.nlink().unwrap();
+        println!("{} nlink={}", e.path().display(), nlink);
+        if e.path().ends_with("single") {
+            assert_eq!(nlink, 1);
+        } else {
+            assert_eq!(nlink, 2);
+        }
+        checked += 1;
+    }
+
+    assert_eq!(checked, 3);
+}
+
+#[test]
+fn walkdir_restrict_to() {
+    println!("\nRestrict to:");
+
+    let tmp = create_temp_dir("walkdir_minimal_restrict_to");
+    for dir in ["alpha", "beta/deep/target", "gamma"] {
+        fs::create_dir_all(tmp.join(dir)).unwrap();
+    }
+    File::create(tmp.join("alpha/a.txt")).unwrap();
+    File::create(tmp.join("beta/b.txt")).unwrap();
+    File::create(tmp.join("beta/deep/skip.txt")).unwrap();
+    File::create(tmp.join("beta/deep/target/t.txt")).unwrap();
+    File::create(tmp.join("gamma/g.txt")).unwrap();
+    File::create(tmp.join("top.txt")).unwrap();
+
+    let walker = WalkDir::new(&tmp)
+        .unwrap()
+        .restrict_to([PathBuf::from("beta/deep/target")]);
+
+    let mut paths = HashSet::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.insert(e.path().to_path_buf());
+    }
+
+    let expected: HashSet<PathBuf> =
+        [tmp.join("beta/deep/target"), tmp.join("beta/deep/target/t.txt")].into();
+    assert_eq!(paths, expected);
+}
+
+#[test]
+fn walkdir_entry_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    println!("\nEntry mode:");
+
+    let tmp = create_temp_dir("walkdir_minimal_entry_mode");
+    File::create(tmp.join("script.sh")).unwrap();
+    File::create(tmp.join("private.txt")).unwrap();
+    fs::set_permissions(tmp.join("script.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+    fs::set_permissions(tmp.join("private.txt"), fs::Permissions::from_mode(0o640)).unwrap();
+
+    for entry in WalkDir::new(&tmp).unwrap() {
+        let e = entry.unwrap();
+        let mode = e.mode().unwrap();
+        let perms = e.permissions_string().unwrap();
+        println!("{} {:o} {}", e.path().display(), mode, perms);
+        if e.path().ends_with("script.sh") {
+            assert_eq!(mode & 0o777, 0o755);
+            assert_eq!(perms, "rwxr-xr-x");
+        } else {
+            assert_eq!(mode & 0o777, 0o640);
+            assert_eq!(perms, "rw-r-----");
+        }
+    }
+}
+
+#[test]
+fn walkdir_for_each_entry() {
+    println!("\nFor each entry:");
+
+    let tmp = create_temp_dir("walkdir_minimal_for_each_entry");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    File::create(tmp.join("a/one.txt")).unwrap();
+    File::create(tmp.join("a/b/two.txt")).unwrap();
+
+    let mut visited = 0;
+    WalkDir::new(&tmp)
+        .unwrap()
+        .for_each_entry(|e| {
+            println!("{}", e.path().display());
+            visited += 1;
+        })
+        .unwrap();
+
+    assert_eq!(visited, WalkDir::new(&tmp).unwrap().count());
+
+    symlink(tmp.join("missing"), tmp.join("broken")).unwrap();
+    let err = WalkDir::new(&tmp).unwrap().follow_links(true).for_each_entry(|_| {});
+    assert!(err.is_err());
+}
+
+#[test]
+fn walkdir_try_for_each_entry() {
+    println!("\nTry for each entry:");
+
+    let tmp = create_temp_dir("walkdir_minimal_try_for_each_entry");
+    for name in ["a.txt", "b.txt", "poison.txt", "z.txt"] {
+        File::create(tmp.join(name)).unwrap();
+    }
+
+    let mut seen = Vec::new();
+    let result = WalkDir::new(&tmp)
+        .unwrap()
+        .sort_by_meta(|a, _, b, _| a.path().cmp(b.path()))
+        .try_for_each_entry(|e| {
+            println!("{}", e.path().display());
+            seen.push(e.path().to_path_buf());
+            if e.path().ends_with("poison.txt") {
+                return Err(std::io::Error::other("poisoned"));
+            }
+            Ok(())
+        });
+
+    match result {
+        Err(WalkError::Io(e)) => assert_eq!(e.to_string(), "poisoned"),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert_eq!(seen, vec![tmp.join("a.txt"), tmp.join("b.txt"), tmp.join("poison.txt")]);
+}
+
+#[test]
+fn walkdir_coalesce_errors() {
+    println!("\nCoalesce errors:");
+
+    let tmp = create_temp_dir("walkdir_minimal_coalesce_errors");
+    fs::create_dir_all(tmp.join("broken")).unwrap();
+    for i in 0..4 {
+        symlink(tmp.join(format!("missing_{i}")), tmp.join(format!("broken/link_{i}"))).unwrap();
+    }
+
+    let errors = |coalesce: bool| {
+        WalkDir::new(&tmp)
+            .unwrap()
+            .follow_links(true)
+            .coalesce_errors(coalesce)
+            .filter(|r| r.is_err())
+            .inspect(|r| println!("{:?}", r.as_ref().unwrap_err()))
+            .count()
+    };
+
+    assert_eq!(errors(false), 4);
+    assert_eq!(errors(true), 1);
+}
+
+#[test]
+fn walkdir_with_visited() {
+    println!("\nWith visited:");
+
+    let tmp = create_temp_dir("walkdir_minimal_with_visited");
+    fs::create_dir_all(tmp.join("done/inner")).unwrap();
+    fs::create_dir_all(tmp.join("todo")).unwrap();
+    File::create(tmp.join("done/inner/old.txt")).unwrap();
+    File::create(tmp.join("todo/new.txt")).unwrap();
+
+    let md = fs::metadata(tmp.join("done")).unwrap();
+    let walker = WalkDir::new(&tmp).unwrap().with_visited([(md.dev(), md.ino())]);
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("todo/new.txt")));
+    assert!(!paths.iter().any(|p| p.starts_with(tmp.join("done"))));
+}
+
+#[test]
+fn walkdir_entry_descended() {
+    println!("\nEntry descended:");
+
+    let tmp = create_temp_dir("walkdir_minimal_entry_descended");
+    fs::create_dir_all(tmp.join("a/b/c")).unwrap();
+    File::create(tmp.join("a/file.txt")).unwrap();
+
+    let mut checked = 0;
+    for entry in WalkDir::new(&tmp).unwrap().max_depth(1) {
+        let e = entry.unwrap();
+        println!("{} depth={} descended={}", e.path().display(), e.depth(), e.descended());
+        match e.path().strip_prefix(&tmp).unwrap().to_str().unwrap() {
+            "a" => assert!(e.descended()),
+            "a/b" => assert!(!e.descended()),
+            "a/file.txt" => assert!(!e.descended()),
+            other => panic!("unexpected entry {other}"),
+        }
+        checked += 1;
+    }
+
+    assert_eq!(checked, 3);
+}
+
+#[test]
+fn walkdir_posix_relative() {
+    println!("\nPosix relative:");
+
+    let tmp = create_temp_dir("walkdir_minimal_posix_relative");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    File::create(tmp.join("a/b/c.txt")).unwrap();
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().posix_relative(true) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_str().unwrap().to_string());
+    }
+
+    paths.sort();
+    assert_eq!(paths, vec!["a", "a/b", "a/b/c.txt"]);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn walkdir_match_regex() {
+    println!("\nMatch regex:");
+
+    let tmp = create_temp_dir("walkdir_minimal_match_regex");
+    fs::create_dir_all(tmp.join("src/nested")).unwrap();
+    File::create(tmp.join("src/lib.rs")).unwrap();
+    File::create(tmp.join("src/nested/mod.rs")).unwrap();
+    File::create(tmp.join("README.md")).unwrap();
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().match_regex(r"\.rs$").unwrap() {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+    paths.sort();
+    assert_eq!(paths, vec![tmp.join("src/lib.rs"), tmp.join("src/nested/mod.rs")]);
+
+    let none = WalkDir::new(&tmp).unwrap().match_regex(r"\.py$").unwrap();
+    assert_eq!(none.count(), 0);
+
+    assert!(WalkDir::new(&tmp).unwrap().match_regex("(unclosed").is_err());
+}
+
+#[test]
+fn walkdir_symlink_root_followed() {
+    println!("\nSymlink root followed:");
+
+    let target = create_temp_dir("walkdir_minimal_symlink_root_target");
+    fs::create_dir_all(target.join("sub")).unwrap();
+    File::create(target.join("sub/file.txt")).unwrap();
+    let holder = create_temp_dir("walkdir_minimal_symlink_root_holder");
+    let root = holder.join("root_link");
+    symlink(&target, &root).unwrap();
+
+    let entries: Vec<Entry> = WalkDir::new(&root)
+        .unwrap()
+        .follow_links(true)
+        .map(|e| e.unwrap())
+        .collect();
+    for e in &entries {
+        println!("{} depth={}", e.path().display(), e.depth());
+    }
+
+    assert_eq!(entries[0].depth(), 0);
+    assert_eq!(entries[0].path(), root);
+    assert_eq!(entries[0].real_path(), fs::canonicalize(&target).unwrap());
+    assert!(entries.iter().any(|e| e.path() == root.join("sub") && e.depth() == 1));
+    assert!(entries.iter().any(|e| e.path() == root.join("sub/file.txt") && e.depth() == 2));
+    assert_eq!(entries.iter().filter(|e| e.depth() == 0).count(), 1);
+
+    let unfollowed = WalkDir::new(&root).unwrap();
+    assert!(unfollowed.map(|e| e.unwrap()).all(|e| e.depth() > 0));
+
+    let mut relative: Vec<PathBuf> = WalkDir::new(&root)
+        .unwrap()
+        .follow_links(true)
+        .posix_relative(true)
+        .map(|e| e.unwrap().path().to_path_buf())
+        .collect();
+    relative.sort();
+    assert_eq!(
+        relative,
+        vec![PathBuf::from("."), PathBuf::from("sub"), PathBuf::from("sub/file.txt")]
+    );
+
+    // The same layout behind another link hashes the same.
+    let other = create_temp_dir("walkdir_minimal_symlink_root_other");
+    fs::create_dir_all(other.join("sub")).unwrap();
+    File::create(other.join("sub/file.txt")).unwrap();
+    let other_root = holder.join("other_link");
+    symlink(&other, &other_root).unwrap();
+    let hash = |r: &Path| WalkDir::new(r).unwrap().follow_links(true).structure_hash().unwrap();
+    assert_eq!(hash(&root), hash(&other_root));
+}
+
+#[test]
+fn walkdir_fold_entries() {
+    println!("\nFold entries:");
+
+    let tmp = create_temp_dir("walkdir_minimal_fold_entries");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    for name in ["one.txt", "a/two.txt", "a/b/three.txt", "a/b/four.txt"] {
+        File::create(tmp.join(name)).unwrap();
+    }
+
+    let files = WalkDir::new(&tmp)
+        .unwrap()
+        .fold_entries(0, |n, e| {
+            println!("{}", e.path().display());
+            if e.file_type().unwrap().is_file() { n + 1 } else { n }
+        })
+        .unwrap();
+
+    let expected = WalkDir::new(&tmp)
+        .unwrap()
+        .filter(|e| e.as_ref().unwrap().file_type().unwrap().is_file())
+        .count();
+    assert_eq!(files, expected);
+    assert_eq!(files, 4);
+}
+
+#[test]
+fn walkdir_confine_to_root() {
+    println!("\nConfine to root:");
+
+    let tmp = create_temp_dir("walkdir_minimal_confine_root");
+    let outside = create_temp_dir("walkdir_minimal_confine_outside");
+    fs::create_dir_all(tmp.join("real")).unwrap();
+    File::create(tmp.join("real/inner.txt")).unwrap();
+    File::create(outside.join("secret.txt")).unwrap();
+    symlink(tmp.join("real"), tmp.join("in_link")).unwrap();
+    symlink(&outside, tmp.join("out_link")).unwrap();
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(&tmp)
+        .unwrap()
+        .follow_links(true)
+        .detect_loops(false)
+        .confine_to_root(true)
+    {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("in_link/inner.txt")));
+    assert!(paths.contains(&tmp.join("out_link")));
+    assert!(!paths.contains(&tmp.join("out_link/secret.txt")));
+}
+
+#[test]
+fn walkdir_extension_counts() {
+    println!("\nExtension counts:");
+
+    let tmp = create_temp_dir("walkdir_minimal_extension_counts");
+    fs::create_dir_all(tmp.join("src.d")).unwrap();
+    for name in ["main.rs", "src.d/lib.rs", "src.d/util.rs", "Cargo.toml", "Makefile", ".hidden"] {
+        File::create(tmp.join(name)).unwrap();
+    }
+
+    let counts = WalkDir::new(&tmp).unwrap().extension_counts().unwrap();
+    println!("{counts:?}");
+
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts[&OsString::from("rs")], 3);
+    assert_eq!(counts[&OsString::from("toml")], 1);
+    assert_eq!(counts[&OsString::new()], 2);
+}
+
+#[cfg(feature = "unicode")]
+#[test]
+fn walkdir_normalize_nfc() {
+    println!("\nNormalize NFC:");
+
+    let tmp = create_temp_dir("walkdir_minimal_normalize_nfc");
+    // "café" with a combining acute accent (NFD).
+    let nfd = "cafe\u{301}";
+    fs::create_dir_all(tmp.join(nfd)).unwrap();
+    File::create(tmp.join(nfd).join("menu.txt")).unwrap();
+
+    let mut names = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().normalize_nfc(true) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        names.push(e.path().to_path_buf());
+        assert!(e.real_path().exists());
+    }
+
+    assert!(names.contains(&tmp.join("caf\u{e9}")));
+    assert!(names.contains(&tmp.join(nfd).join("menu.txt")));
+}
+
+#[test]
+fn walkdir_ancestors_from_root() {
+    println!("\nAncestors from root:");
+
+    let tmp = create_temp_dir("walkdir_minimal_ancestors_from_root");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    File::create(tmp.join("a/b/c.txt")).unwrap();
+
+    let entry = WalkDir::new(&tmp)
+        .unwrap()
+        .map(|e| e.unwrap())
+        .find(|e| e.depth() == 3)
+        .unwrap();
+    let chain = entry.ancestors_from_root(&tmp);
+    for p in &chain {
+        println!("{}", p.display());
+    }
+
+    assert_eq!(chain, vec![tmp.clone(), tmp.join("a"), tmp.join("a/b")]);
+    assert!(entry.ancestors_from_root(Path::new("/elsewhere")).is_empty());
+
+    let root_entry = Entry::new(tmp.clone(), 0);
+    assert!(root_entry.ancestors_from_root(&tmp).is_empty());
+}
+
+#[test]
+fn walkdir_quick_stat_matches_std() {
+    println!("\nQuick stat matches std:");
+
+    let tmp = create_temp_dir("walkdir_minimal_quick_stat");
+    fs::create_dir_all(tmp.join("dir/nested")).unwrap();
+    fs::write(tmp.join("dir/data.bin"), vec![7u8; 1234]).unwrap();
+    File::create(tmp.join("empty")).unwrap();
+    symlink(tmp.join("dir"), tmp.join("dir_link")).unwrap();
+
+    for entry in WalkDir::new(&tmp).unwrap() {
+        let e = entry.unwrap();
+        for follow in [true, false] {
+            let fast = platform::quick_stat(e.path(), follow).unwrap();
+            let slow = platform::std_stat(e.path(), follow).unwrap();
+            println!("{} follow={} dir={} len={}", e.path().display(), follow, fast.is_dir, fast.len);
+            assert_eq!(fast.is_dir, slow.is_dir);
+            assert_eq!(fast.is_file, slow.is_file);
+            if fast.is_file {
+                assert_eq!(fast.len, slow.len);
+            }
+        }
+    }
+}
+
+#[test]
+fn walkdir_reset() {
+    println!("\nReset:");
+
+    let tmp = create_temp_dir("walkdir_minimal_reset");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    File::create(tmp.join("a/b/one.txt")).unwrap();
+    File::create(tmp.join("two.txt")).unwrap();
+
+    let mut walker = WalkDir::new(&tmp).unwrap().shuffle_children(3);
+    let first: Vec<PathBuf> = walker.by_ref().map(|e| e.unwrap().path().to_path_buf()).collect();
+    assert!(walker.next().is_none());
+
+    walker.reset();
+    let second: Vec<PathBuf> = walker.by_ref().map(|e| e.unwrap().path().to_path_buf()).collect();
+    for p in &second {
+        println!("{}", p.display());
+    }
+
+    assert_eq!(first.len(), 4);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn walkdir_sort_natural() {
+    println!("\nSort natural:");
+
+    let tmp = create_temp_dir("walkdir_minimal_sort_natural");
+    for name in ["file10", "file2", "file1", "file02b", "other"] {
+        File::create(tmp.join(name)).unwrap();
+    }
+
+    let mut names = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().sort_natural(true) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        names.push(e.path().file_name().unwrap().to_str().unwrap().to_string());
+    }
+
+    assert_eq!(names, vec!["file1", "file2", "file02b", "file10", "other"]);
+}
+
+#[test]
+fn walkdir_entry_as_bytes() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    println!("\nEntry as bytes:");
+
+    let tmp = create_temp_dir("walkdir_minimal_entry_as_bytes");
+    let raw: &[u8] = b"bad\xffname\xfe.bin";
+    File::create(tmp.join(OsStr::from_bytes(raw))).unwrap();
+
+    let entry = WalkDir::new(&tmp).unwrap().next().unwrap().unwrap();
+    println!("{}", entry.path().display());
+
+    let mut expected = tmp.as_os_str().as_bytes().to_vec();
+    expected.push(b'/');
+    expected.extend_from_slice(raw);
+    assert_eq!(entry.as_bytes(), expected.as_slice());
+    assert!(entry.path().to_str().is_none());
+}
+
+#[test]
+fn walkdir_max_path_len() {
+    println!("\nMax path len:");
+
+    let tmp = create_temp_dir("walkdir_minimal_max_path_len");
+    let deep = tmp.join("level_one/level_two/level_three");
+    fs::create_dir_all(&deep).unwrap();
+    File::create(deep.join("file.txt")).unwrap();
+    File::create(tmp.join("short.txt")).unwrap();
+
+    let limit = tmp.join("level_one/level_two").as_os_str().len();
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().max_path_len(limit) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        assert!(e.path().as_os_str().len() <= limit);
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("short.txt")));
+    assert!(paths.contains(&tmp.join("level_one/level_two")));
+    assert!(!paths.iter().any(|p| p.starts_with(&deep)));
+}
+
+#[test]
+fn walkdir_progress_every() {
+    println!("\nProgress every:");
+
+    let tmp = create_temp_dir("walkdir_minimal_progress_every");
+    for d in 0..5 {
+        let dir = tmp.join(format!("dir_{d}"));
+        fs::create_dir_all(&dir).unwrap();
+        for f in 0..9 {
+            File::create(dir.join(format!("file_{f}"))).unwrap();
+        }
+    }
+
+    let reports = Rc::new(std::cell::RefCell::new(Vec::new()));
+    let sink = Rc::clone(&reports);
+    let total = WalkDir::new(&tmp)
+        .unwrap()
+        .progress_every(10, move |n| sink.borrow_mut().push(n))
+        .count();
+
+    println!("{:?}", reports.borrow());
+    assert_eq!(total, 50);
+    assert_eq!(*reports.borrow(), vec![10, 20, 30, 40, 50]);
+}
+
+#[test]
+fn walkdir_boundary_marker() {
+    println!("\nBoundary marker:");
+
+    let tmp = create_temp_dir("walkdir_minimal_boundary_marker");
+    fs::create_dir_all(tmp.join("crates/outer/inner")).unwrap();
+    fs::create_dir_all(tmp.join("docs")).unwrap();
+    File::create(tmp.join("crates/outer/Cargo.toml")).unwrap();
+    File::create(tmp.join("crates/outer/inner/Cargo.toml")).unwrap();
+    File::create(tmp.join("docs/guide.md")).unwrap();
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().boundary_marker("Cargo.toml") {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert!(paths.contains(&tmp.join("crates/outer")));
+    assert!(paths.contains(&tmp.join("docs/guide.md")));
+    assert!(!paths.contains(&tmp.join("crates/outer/Cargo.toml")));
+    assert!(!paths.contains(&tmp.join("crates/outer/inner")));
+}
+
+#[test]
+fn walkdir_duplicate_names() {
+    println!("\nDuplicate names:");
+
+    let tmp = create_temp_dir("walkdir_minimal_duplicate_names");
+    fs::create_dir_all(tmp.join("docs")).unwrap();
+    fs::create_dir_all(tmp.join("pkg")).unwrap();
+    File::create(tmp.join("README.md")).unwrap();
+    File::create(tmp.join("docs/README.md")).unwrap();
+    File::create(tmp.join("pkg/lib.rs")).unwrap();
+
+    let dups = WalkDir::new(&tmp).unwrap().duplicate_names().unwrap();
+    println!("{dups:?}");
+
+    assert_eq!(dups.len(), 1);
+    let mut paths = dups[&OsString::from("README.md")].clone();
+    paths.sort();
+    assert_eq!(paths, vec![tmp.join("README.md"), tmp.join("docs/README.md")]);
+}
+
+#[test]
+fn walkdir_hardlink_groups() {
+    println!("\nHardlink groups:");
+
+    let tmp = create_temp_dir("walkdir_minimal_hardlink_groups");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    fs::write(tmp.join("original.txt"), "shared").unwrap();
+    fs::hard_link(tmp.join("original.txt"), tmp.join("sub/linked.txt")).unwrap();
+    fs::write(tmp.join("alone.txt"), "shared").unwrap();
+
+    let groups = WalkDir::new(&tmp).unwrap().hardlink_groups().unwrap();
+    for group in &groups {
+        println!("{:?}", group.iter().map(|e| e.path()).collect::<Vec<_>>());
+    }
+
+    assert_eq!(groups.len(), 1);
+    let mut paths: Vec<PathBuf> = groups[0].iter().map(|e| e.path().to_path_buf()).collect();
+    paths.sort();
+    assert_eq!(paths, vec![tmp.join("original.txt"), tmp.join("sub/linked.txt")]);
+}
+
+#[test]
+fn walkdir_transparent_dirs() {
+    println!("\nTransparent dirs:");
+
+    let tmp = create_temp_dir("walkdir_minimal_transparent_dirs");
+    fs::create_dir_all(tmp.join("pkg/__intermediate__/mod")).unwrap();
+    File::create(tmp.join("pkg/lib.rs")).unwrap();
+    File::create(tmp.join("pkg/__intermediate__/gen.rs")).unwrap();
+    File::create(tmp.join("pkg/__intermediate__/mod/deep.rs")).unwrap();
+
+    let mut depths = std::collections::HashMap::new();
+    for entry in WalkDir::new(&tmp)
+        .unwrap()
+        .transparent_dirs(["__intermediate__".to_string()])
+    {
+        let e = entry.unwrap();
+        println!("{} depth={}", e.path().display(), e.depth());
+        depths.insert(e.path().strip_prefix(&tmp).unwrap().to_path_buf(), e.depth());
+    }
+
+    assert_eq!(depths[Path::new("pkg")], 1);
+    assert_eq!(depths[Path::new("pkg/lib.rs")], 2);
+    assert_eq!(depths[Path::new("pkg/__intermediate__")], 2);
+    assert_eq!(depths[Path::new("pkg/__intermediate__/gen.rs")], 2);
+    assert_eq!(depths[Path::new("pkg/__intermediate__/mod")], 2);
+    assert_eq!(depths[Path::new("pkg/__intermediate__/mod/deep.rs")], 3);
+}
+
+#[test]
+fn walkdir_broken_symlinks() {
+    println!("\nBroken symlinks:");
+
+    let tmp = create_temp_dir("walkdir_minimal_broken_symlinks");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    File::create(tmp.join("target.txt")).unwrap();
+    symlink(tmp.join("target.txt"), tmp.join("good")).unwrap();
+    symlink(tmp.join("gone.txt"), tmp.join("sub/bad")).unwrap();
+
+    let broken = WalkDir::new(&tmp).unwrap().broken_symlinks().unwrap();
+    for p in &broken {
+        println!("{}", p.display());
+    }
+
+    assert_eq!(broken, vec![tmp.join("sub/bad")]);
+
+    // Display transforms change the reported path, not the one that is checked.
+    symlink(tmp.join("target.txt"), tmp.join("GOOD")).unwrap();
+    let lowered = WalkDir::new(&tmp).unwrap().lowercase_names(true).broken_symlinks().unwrap();
+    assert_eq!(lowered, vec![tmp.join("sub/bad")]);
+    let relative = WalkDir::new(&tmp).unwrap().posix_relative(true).broken_symlinks().unwrap();
+    assert_eq!(relative, vec![PathBuf::from("sub/bad")]);
+}
+
+#[test]
+fn walkdir_entry_raw_depth() {
+    println!("\nEntry raw depth:");
+
+    let tmp = create_temp_dir("walkdir_minimal_entry_raw_depth");
+    fs::create_dir_all(tmp.join("a/skip/b")).unwrap();
+    File::create(tmp.join("a/skip/b/leaf.txt")).unwrap();
+
+    let mut checked = 0;
+    for entry in WalkDir::new(&tmp)
+        .unwrap()
+        .transparent_dirs(["skip".to_string()])
+    {
+        let e = entry.unwrap();
+        println!("{} depth={} raw={}", e.path().display(), e.depth(), e.raw_depth());
+        let nesting = e.path().strip_prefix(&tmp).unwrap().components().count();
+        assert_eq!(e.raw_depth(), nesting);
+        if e.path().ends_with("leaf.txt") {
+            assert_eq!(e.depth(), 3);
+            assert_eq!(e.raw_depth(), 4);
+            checked += 1;
+        }
+    }
+
+    assert_eq!(checked, 1);
+}
+
+#[test]
+fn walkdir_diff_walk() {
+    println!("\nDiff walk:");
+
+    let left = create_temp_dir("walkdir_minimal_diff_walk_left");
+    let right = create_temp_dir("walkdir_minimal_diff_walk_right");
+    for root in [&left, &right] {
+        fs::create_dir_all(root.join("src")).unwrap();
+        File::create(root.join("src/lib.rs")).unwrap();
+    }
+    File::create(left.join("old.txt")).unwrap();
+    File::create(right.join("src/new.rs")).unwrap();
+
+    let items = diff_walk(WalkDir::new(&left).unwrap(), WalkDir::new(&right).unwrap()).unwrap();
+    for item in &items {
+        println!("{item:?}");
+    }
+
+    assert_eq!(
+        items,
+        vec![
+            DiffItem::OnlyLeft(PathBuf::from("old.txt")),
+            DiffItem::Both(PathBuf::from("src")),
+            DiffItem::Both(PathBuf::from("src/lib.rs")),
+            DiffItem::OnlyRight(PathBuf::from("src/new.rs")),
+        ]
+    );
+}
+
+#[test]
+fn walkdir_metadata_error_policy() {
+    println!("\nMetadata error policy:");
+
+    let tmp = create_temp_dir("walkdir_minimal_metadata_error_policy");
+    for name in ["a_first", "c_last"] {
+        File::create(tmp.join(name)).unwrap();
+    }
+    symlink(tmp.join("missing"), tmp.join("b_broken")).unwrap();
+
+    let run = |policy: MetadataErrorPolicy| -> (usize, usize) {
+        let mut ok = 0;
+        let mut errors = 0;
+        for entry in WalkDir::new(&tmp)
+            .unwrap()
+            .follow_links(true)
+            .sort_natural(true)
+            .metadata_error_policy(policy)
+        {
+            match entry {
+                Ok(e) => {
+                    println!("{policy:?}: {}", e.path().display());
+                    ok += 1;
+                }
+                Err(e) => {
+                    println!("{policy:?}: error {e}");
+                    errors += 1;
+                }
+            }
+        }
+        (ok, errors)
+    };
+
+    assert_eq!(run(MetadataErrorPolicy::Yield), (2, 1));
+    assert_eq!(run(MetadataErrorPolicy::Skip), (2, 0));
+    assert_eq!(run(MetadataErrorPolicy::Abort), (1, 1));
+}
+
+#[test]
+fn walkdir_open_handles() {
+    println!("\nOpen handles:");
+
+    let tmp = create_temp_dir("walkdir_minimal_open_handles");
+    fs::create_dir_all(tmp.join("a/b/c/d")).unwrap();
+    File::create(tmp.join("a/b/c/d/leaf.txt")).unwrap();
+
+    let mut walker = WalkDir::new(&tmp).unwrap();
+    assert_eq!(walker.open_handles(), 0);
+
+    let mut counts = Vec::new();
+    while let Some(entry) = walker.next() {
+        let e = entry.unwrap();
+        println!("{} open={}", e.path().display(), walker.open_handles());
+        counts.push(walker.open_handles());
+    }
+
+    assert_eq!(counts, vec![2, 3, 4, 5, 5]);
+    assert_eq!(walker.open_handles(), 0);
+}
+
+#[test]
+fn walkdir_skip_root() {
+    println!("\nSkip root:");
+
+    let target = create_temp_dir("walkdir_minimal_skip_root_target");
+    fs::create_dir_all(target.join("sub")).unwrap();
+    File::create(target.join("sub/file.txt")).unwrap();
+    let holder = create_temp_dir("walkdir_minimal_skip_root_holder");
+    symlink(&target, holder.join("link")).unwrap();
+
+    let entries: Vec<Entry> = WalkDir::new(holder.join("link"))
+        .unwrap()
+        .follow_links(true)
+        .skip_root(true)
+        .map(|e| e.unwrap())
+        .collect();
+    for e in &entries {
+        println!("{} depth={}", e.path().display(), e.depth());
+    }
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|e| e.depth() > 0));
+
+    let file_root = WalkDir::new(target.join("sub/file.txt")).unwrap().skip_root(true);
+    assert_eq!(file_root.count(), 0);
+}
+
+#[test]
+fn walkdir_loop_detection_mode() {
+    println!("\nLoop detection mode:");
+
+    let tmp = create_temp_dir("walkdir_minimal_loop_detection_mode");
+    fs::create_dir_all(tmp.join("a/b")).unwrap();
+    File::create(tmp.join("a/b/file.txt")).unwrap();
+    symlink(tmp.join("a"), tmp.join("a/b/back")).unwrap();
+
+    for mode in [LoopMode::Inode, LoopMode::Canonical] {
+        let paths: Vec<PathBuf> = WalkDir::new(&tmp)
+            .unwrap()
+            .follow_links(true)
+            .loop_detection_mode(mode)
+            .map(|e| e.unwrap().path().to_path_buf())
+            .collect();
+        for p in &paths {
+            println!("{mode:?}: {}", p.display());
+        }
+
+        assert_eq!(paths.len(), 3);
+        assert!(!paths.iter().any(|p| p.starts_with(tmp.join("a/b/back"))));
+    }
+}
+
+#[test]
+fn walkdir_write_paths_to() {
+    println!("\nWrite paths to:");
+
+    let tmp = create_temp_dir("walkdir_minimal_write_paths_to");
+    fs::create_dir_all(tmp.join("tree/sub")).unwrap();
+    File::create(tmp.join("tree/a.txt")).unwrap();
+    File::create(tmp.join("tree/sub/b.txt")).unwrap();
+    let out = tmp.join("list.txt");
+    fs::write(&out, "stale contents\n".repeat(10)).unwrap();
+
+    let count = WalkDir::new(tmp.join("tree"))
+        .unwrap()
+        .write_paths_to(&out)
+        .unwrap();
+
+    let text = fs::read_to_string(&out).unwrap();
+    print!("{text}");
+    let mut lines: Vec<&str> = text.lines().collect();
+    lines.sort();
+
+    assert_eq!(count, 3);
+    let expected: Vec<String> = ["tree/a.txt", "tree/sub", "tree/sub/b.txt"]
+        .iter()
+        .map(|p| tmp.join(p).to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(lines, expected);
+}
+
+#[cfg(feature = "unicode-width")]
+#[test]
+fn walkdir_entry_display_width() {
+    println!("\nEntry display width:");
+
+    let tmp = create_temp_dir("walkdir_minimal_entry_display_width");
+    File::create(tmp.join("漢字")).unwrap();
+    File::create(tmp.join("plain.txt")).unwrap();
+
+    let mut checked = 0;
+    for entry in WalkDir::new(&tmp).unwrap() {
+        let e = entry.unwrap();
+        println!("{} width={}", e.path().display(), e.display_width());
+        if e.path().ends_with("漢字") {
+            assert_eq!(e.display_width(), 4);
+        } else {
+            assert_eq!(e.display_width(), "plain.txt".len());
+        }
+        checked += 1;
+    }
+
+    assert_eq!(checked, 2);
+}
+
+#[test]
+fn walkdir_always_yield_ancestors() {
+    println!("\nAlways yield ancestors:");
+
+    let tmp = create_temp_dir("walkdir_minimal_always_yield_ancestors");
+    fs::create_dir_all(tmp.join("hidden_a/hidden_b")).unwrap();
+    fs::create_dir_all(tmp.join("hidden_c")).unwrap();
+    File::create(tmp.join("hidden_a/hidden_b/match.rs")).unwrap();
+    File::create(tmp.join("hidden_a/hidden_b/skip.txt")).unwrap();
+    File::create(tmp.join("hidden_c/skip.txt")).unwrap();
+
+    let walker = WalkDir::new(&tmp)
+        .unwrap()
+        .filter_entry(|e| e.path().extension().is_some_and(|x| x == "rs"))
+        .always_yield_ancestors(true);
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert_eq!(
+        paths,
+        vec![
+            tmp.join("hidden_a"),
+            tmp.join("hidden_a/hidden_b"),
+            tmp.join("hidden_a/hidden_b/match.rs"),
+        ]
+    );
+}
+
+#[test]
+fn walkdir_quick_count() {
+    println!("\nQuick count:");
+
+    let tmp = create_temp_dir("walkdir_minimal_quick_count");
+    fs::create_dir_all(tmp.join("a/b/c")).unwrap();
+    fs::create_dir_all(tmp.join("d")).unwrap();
+    for name in ["top.txt", "a/one.txt", "a/b/two.txt", "a/b/c/three.txt", "d/four.txt"] {
+        File::create(tmp.join(name)).unwrap();
+    }
+
+    let quick = WalkDir::new(&tmp).unwrap().quick_count().unwrap();
+    let full = WalkDir::new(&tmp).unwrap().count();
+    println!("quick={quick} full={full}");
+    assert_eq!(quick, full);
+
+    let quick = WalkDir::new(&tmp).unwrap().max_depth(2).quick_count().unwrap();
+    let full = WalkDir::new(&tmp).unwrap().max_depth(2).count();
+    assert_eq!(quick, full);
+
+    let holder = create_temp_dir("walkdir_minimal_quick_count_holder");
+    symlink(tmp.join("a"), holder.join("link")).unwrap();
+    let quick = WalkDir::new(holder.join("link")).unwrap().quick_count().unwrap();
+    let full = WalkDir::new(holder.join("link")).unwrap().count();
+    println!("linked root: quick={quick} full={full}");
+    assert_eq!(quick, 5);
+    assert_eq!(quick, full);
+}
+
+#[test]
+fn walkdir_to_nested_map() {
+    println!("\nTo nested map:");
+
+    let tmp = create_temp_dir("walkdir_minimal_to_nested_map");
+    fs::create_dir_all(tmp.join("src/bin")).unwrap();
+    fs::create_dir_all(tmp.join("empty")).unwrap();
+    for name in ["Cargo.toml", "src/lib.rs", "src/bin/main.rs"] {
+        File::create(tmp.join(name)).unwrap();
+    }
+
+    let first = WalkDir::new(&tmp).unwrap().to_nested_map().unwrap();
+    let second = WalkDir::new(&tmp).unwrap().shuffle_children(9).to_nested_map().unwrap();
+    println!("{first:?}");
+    assert_eq!(first, second);
+
+    let names = |list: &[&str]| list.iter().map(OsString::from).collect();
+    let bin = NestedDir {
+        files: names(&["main.rs"]),
+        dirs: Default::default(),
+    };
+    let src = NestedDir {
+        files: names(&["lib.rs"]),
+        dirs: [(OsString::from("bin"), bin)].into(),
+    };
+    let expected = NestedDir {
+        files: names(&["Cargo.toml"]),
+        dirs: [(OsString::from("empty"), NestedDir::default()), (OsString::from("src"), src)].into(),
+    };
+    assert_eq!(first, expected);
+}
+
+#[test]
+fn walkdir_older_than() {
+    println!("\nOlder than:");
+
+    let tmp = create_temp_dir("walkdir_minimal_older_than");
+    fs::create_dir_all(tmp.join("logs")).unwrap();
+    File::create(tmp.join("fresh.log")).unwrap();
+    for name in ["stale.log", "logs/rotated.log"] {
+        let f = File::create(tmp.join(name)).unwrap();
+        f.set_modified(SystemTime::now() - Duration::from_secs(3 * 3600)).unwrap();
+    }
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().older_than(Duration::from_secs(3600)) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+    paths.sort();
+
+    assert_eq!(paths, vec![tmp.join("logs/rotated.log"), tmp.join("stale.log")]);
+}
+
+#[test]
+fn walkdir_diff_against() {
+    println!("\nDiff against baseline:");
+
+    let tmp = create_temp_dir("walkdir_minimal_diff_against");
+    fs::create_dir_all(tmp.join("sub")).unwrap();
+    fs::write(tmp.join("keep.txt"), "same").unwrap();
+    fs::write(tmp.join("sub/edit.txt"), "before").unwrap();
+
+    let mut baseline = std::collections::HashMap::new();
+    for entry in WalkDir::new(&tmp).unwrap() {
+        let e = entry.unwrap();
+        let md = e.symlink_metadata().unwrap();
+        let rel = e.path().strip_prefix(&tmp).unwrap().to_path_buf();
+        baseline.insert(rel, (md.len(), md.modified().unwrap()));
+    }
+
+    fs::write(tmp.join("sub/edit.txt"), "after the edit").unwrap();
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().diff_against(baseline) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().to_path_buf());
+    }
+
+    assert_eq!(paths, vec![tmp.join("sub/edit.txt")]);
+}
+
+#[test]
+fn walkdir_allocated_size() {
+    println!("\nAllocated size:");
+
+    let tmp = create_temp_dir("walkdir_minimal_allocated_size");
+    let f = File::create(tmp.join("sparse.img")).unwrap();
+    f.set_len(8 * 1024 * 1024).unwrap();
+
+    let entry = WalkDir::new(&tmp).unwrap().next().unwrap().unwrap();
+    let allocated = entry.allocated_size().unwrap();
+    let apparent = entry.metadata().unwrap().len();
+    println!("{} allocated={allocated} apparent={apparent}", entry.path().display());
+
+    assert_eq!(apparent, 8 * 1024 * 1024);
+    assert!(allocated < apparent);
+}
+
+#[test]
+fn walkdir_subtrees_under() {
+    println!("\nSubtrees under cap:");
+
+    let tmp = create_temp_dir("walkdir_minimal_subtrees_under");
+    fs::create_dir_all(tmp.join("big/nested")).unwrap();
+    fs::create_dir_all(tmp.join("small")).unwrap();
+    fs::write(tmp.join("big/a.bin"), [0u8; 600]).unwrap();
+    fs::write(tmp.join("big/nested/b.bin"), [0u8; 1200]).unwrap();
+    fs::write(tmp.join("small/c.bin"), [0u8; 100]).unwrap();
+
+    let dirs = WalkDir::new(&tmp).unwrap().subtrees_under(1000).unwrap();
+    let mut paths: Vec<PathBuf> = dirs.iter().map(|d| d.path().to_path_buf()).collect();
+    paths.sort();
+    println!("{paths:?}");
+
+    assert_eq!(paths, vec![tmp.join("small")]);
+}
+
+#[test]
+fn walkdir_delete_all() {
+    println!("\nDelete all:");
+
+    let tmp = create_temp_dir("walkdir_minimal_delete_all");
+    let outside = create_temp_dir("walkdir_minimal_delete_all_outside");
+    File::create(outside.join("keep.txt")).unwrap();
+
+    let root = tmp.join("tree");
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    fs::create_dir_all(root.join("empty")).unwrap();
+    File::create(root.join("top.txt")).unwrap();
+    File::create(root.join("a/b/deep.txt")).unwrap();
+    symlink(&outside, root.join("a/link")).unwrap();
+
+    let removed = WalkDir::new(&root).unwrap().delete_all().unwrap();
+    println!("removed {removed}");
+
+    // root, a, a/b, a/b/deep.txt, a/link, empty, top.txt
+    assert_eq!(removed, 7);
+    assert!(!root.exists());
+    assert!(outside.join("keep.txt").exists());
+
+    // Display transforms must not redirect deletions to paths under the CWD.
+    fs::create_dir_all(root.join("sub")).unwrap();
+    File::create(root.join("sub/x.txt")).unwrap();
+    let removed = WalkDir::new(&root).unwrap().posix_relative(true).delete_all().unwrap();
+    assert_eq!(removed, 3);
+    assert!(!root.exists());
+
+    // A linked root is unlinked without touching what it points to.
+    File::create(outside.join("precious.txt")).unwrap();
+    symlink(&outside, tmp.join("link_root")).unwrap();
+    let removed = WalkDir::new(tmp.join("link_root")).unwrap().delete_all().unwrap();
+    assert_eq!(removed, 1);
+    assert!(fs::symlink_metadata(tmp.join("link_root")).is_err());
+    assert!(outside.join("precious.txt").exists());
+    assert!(outside.join("keep.txt").exists());
+}
+
+#[test]
+fn walkdir_delete_all_ignores_narrowing() {
+    println!("\nDelete all ignores narrowing options:");
+
+    let tmp = create_temp_dir("walkdir_minimal_delete_all_narrowing");
+    let build = |name: &str| {
+        let root = tmp.join(name);
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/deep.txt"), b"deep").unwrap();
+        File::create(root.join("a/skip.log")).unwrap();
+        fs::write(root.join("top.txt"), b"top").unwrap();
+        root
+    };
+
+    // root, a, a/b, a/b/deep.txt, a/skip.log, top.txt
+    type Narrow = fn(WalkDir) -> WalkDir;
+    let cases: [(&str, Narrow); 7] = [
+        ("filter", |w| w.filter_entry(|e| !e.path().ends_with("a"))),
+        ("globs", |w| w.exclude_globs(vec!["*.log".to_string()])),
+        ("restrict", |w| w.restrict_to(vec![PathBuf::from("a")])),
+        ("depth", |w| w.max_depth(0)),
+        ("children", |w| w.max_children(1)),
+        ("stop", |w| w.stop_when(|_| true)),
+        ("budget", |w| w.byte_budget(0)),
+    ];
+    for (name, narrow) in cases {
+        let root = build(name);
+        let removed = narrow(WalkDir::new(&root).unwrap()).delete_all().unwrap();
+        println!("{name}: removed {removed}");
+        assert_eq!(removed, 6, "{name}");
+        assert!(!root.exists(), "{name}");
+    }
+
+    let root = build("visited");
+    let md = fs::metadata(root.join("a")).unwrap();
+    let removed = WalkDir::new(&root)
+        .unwrap()
+        .with_visited(vec![(md.dev(), md.ino())])
+        .delete_all()
+        .unwrap();
+    assert_eq!(removed, 6);
+    assert!(!root.exists());
+}
+
+#[test]
+fn walkdir_catch_filter_panics() {
+    println!("\nCatch filter panics:");
+
+    let tmp = create_temp_dir("walkdir_minimal_catch_filter_panics");
+    for name in ["a.txt", "boom.txt", "c.txt"] {
+        File::create(tmp.join(name)).unwrap();
+    }
+
+    let mut paths = Vec::new();
+    let mut panicked = Vec::new();
+    let walker = WalkDir::new(&tmp)
+        .unwrap()
+        .sort_natural(true)
+        .catch_filter_panics(true)
+        .filter_entry(|e| {
+            if e.path().ends_with("boom.txt") {
+                panic!("filter blew up");
+            }
+            true
+        });
+    for entry in walker {
+        match entry {
+            Ok(e) => paths.push(e.path().to_path_buf()),
+            Err(WalkError::FilterPanic(p)) => panicked.push(p),
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+    println!("{paths:?} {panicked:?}");
+
+    assert_eq!(panicked, vec![tmp.join("boom.txt")]);
+    assert_eq!(paths, vec![tmp.join("a.txt"), tmp.join("c.txt")]);
+}
+
+#[test]
+fn walkdir_recent_dirs_first() {
+    println!("\nRecent dirs first:");
+
+    let tmp = create_temp_dir("walkdir_minimal_recent_dirs_first");
+    let base = SystemTime::now() - Duration::from_secs(3600);
+    for (i, name) in ["old", "newest", "middle"].iter().enumerate() {
+        fs::create_dir_all(tmp.join(name)).unwrap();
+        File::create(tmp.join(name).join("inner.txt")).unwrap();
+        let age = match i {
+            0 => 0,
+            1 => 600,
+            _ => 300,
+        };
+        File::open(tmp.join(name)).unwrap().set_modified(base + Duration::from_secs(age)).unwrap();
+    }
+    File::create(tmp.join("b.txt")).unwrap();
+    File::create(tmp.join("a.txt")).unwrap();
+
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(&tmp).unwrap().recent_dirs_first(true) {
+        let e = entry.unwrap();
+        println!("{}", e.path().display());
+        paths.push(e.path().strip_prefix(&tmp).unwrap().to_path_buf());
+    }
+
+    let expected: Vec<PathBuf> = [
+        "newest",
+        "newest/inner.txt",
+        "middle",
+        "middle/inner.txt",
+        "old",
+        "old/inner.txt",
+        "a.txt",
+        "b.txt",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    assert_eq!(paths, expected);
+}
+
+#[test]
+fn walkdir_access_checks() {
+    println!("\nAccess checks:");
+
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = create_temp_dir("walkdir_minimal_access_checks");
+    fs::write(tmp.join("open.txt"), "x").unwrap();
+    fs::write(tmp.join("locked.txt"), "x").unwrap();
+    fs::write(tmp.join("run.sh"), "x").unwrap();
+    fs::set_permissions(tmp.join("open.txt"), fs::Permissions::from_mode(0o644)).unwrap();
+    fs::set_permissions(tmp.join("locked.txt"), fs::Permissions::from_mode(0o000)).unwrap();
+    fs::set_permissions(tmp.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    // Root bypasses read and write checks when the effective ids are consulted.
+    #[cfg(feature = "access")]
+    let privileged = unsafe { libc::geteuid() } == 0;
+    #[cfg(not(feature = "access"))]
+    let privileged = false;
+
+    for entry in WalkDir::new(&tmp).unwrap() {
+        let e = entry.unwrap();
+        println!(
+            "{} r={} w={} x={}",
+            e.path().display(),
+            e.is_readable(),
+            e.is_writable(),
+            e.is_executable()
+        );
+        match e.path().file_name().and_then(|n| n.to_str()) {
+            Some("open.txt") => {
+                assert!(e.is_readable());
+                assert!(!e.is_executable());
+            }
+            Some("locked.txt") => {
+                assert_eq!(e.is_readable(), privileged);
+                assert_eq!(e.is_writable(), privileged);
+                assert!(!e.is_executable());
+            }
+            Some("run.sh") => assert!(e.is_executable()),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn walkdir_content_duplicates() {
+    println!("\nContent duplicates:");
+
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    fn hash_contents(path: &Path) -> std::io::Result<u64> {
+        let mut hasher = DefaultHasher::new();
+        fs::read(path)?.hash(&mut hasher);
+        Ok(hasher.finish())
+    }
+
+    let tmp = create_temp_dir("walkdir_minimal_content_duplicates");
+    fs::create_dir_all(tmp.join("backup")).unwrap();
+    fs::write(tmp.join("report.txt"), "quarterly numbers").unwrap();
+    fs::write(tmp.join("backup/copy.txt"), "quarterly numbers").unwrap();
+    fs::write(tmp.join("notes.txt"), "something else").unwrap();
+
+    let groups = WalkDir::new(&tmp).unwrap().content_duplicates(hash_contents).unwrap();
+    println!("{groups:?}");
+
+    assert_eq!(groups, vec![vec![tmp.join("backup/copy.txt"), tmp.join("report.txt")]]);
+}
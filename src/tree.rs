@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::{Entry, WalkDir, WalkError};
+
+#[derive(Clone, Debug)]
+pub struct SizedNode {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub children: Vec<SizedNode>,
+}
+
+impl SizedNode {
+    fn new(path: PathBuf, is_dir: bool, size: u64) -> Self {
+        Self {
+            path,
+            is_dir,
+            size,
+            children: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NestedDir {
+    pub files: BTreeSet<OsString>,
+    pub dirs: BTreeMap<OsString, NestedDir>,
+}
+
+fn fold_top(stack: &mut Vec<SizedNode>) {
+    if let Some(node) = stack.pop()
+        && let Some(parent) = stack.last_mut()
+    {
+        parent.size += node.size;
+        parent.children.push(node);
+    }
+}
+
+impl WalkDir {
+    pub fn sized_tree(self) -> Result<SizedNode, WalkError> {
+        let follow = self.is_following_links();
+        let mut stack = vec![SizedNode::new(self.root().to_path_buf(), true, 0)];
+
+        for entry in self {
+            let entry = entry?;
+            let md = if follow {
+                entry.metadata()?
+            } else {
+                entry.symlink_metadata()?
+            };
+            let size = if md.is_dir() { 0 } else { md.len() };
+
+            // `depth` is flattened by transparent directories; nesting follows the real layout.
+            if entry.raw_depth() == 0 {
+                stack[0] = SizedNode::new(entry.path().to_path_buf(), md.is_dir(), size);
+                continue;
+            }
+
+            while stack.len() > entry.raw_depth() {
+                fold_top(&mut stack);
+            }
+            stack.push(SizedNode::new(entry.path().to_path_buf(), md.is_dir(), size));
+        }
+
+        while stack.len() > 1 {
+            fold_top(&mut stack);
+        }
+        Ok(stack.remove(0))
+    }
+
+    pub fn to_nested_map(self) -> Result<NestedDir, WalkError> {
+        let follow = self.is_following_links();
+        let roots = self.roots.clone();
+        let mut tree = NestedDir::default();
+
+        for entry in self {
+            let entry = entry?;
+            let Ok(rel) = entry.path().strip_prefix(&roots[entry.root_index()]) else {
+                continue;
+            };
+            let mut parts: Vec<OsString> =
+                rel.components().map(|c| c.as_os_str().to_os_string()).collect();
+            let Some(name) = parts.pop() else {
+                continue;
+            };
+            let is_dir = if follow {
+                entry.metadata()?.is_dir()
+            } else {
+                entry.file_type()?.is_dir()
+            };
+
+            let mut node = &mut tree;
+            for part in parts {
+                node = node.dirs.entry(part).or_default();
+            }
+            if is_dir {
+                node.dirs.entry(name).or_default();
+            } else {
+                node.files.insert(name);
+            }
+        }
+        Ok(tree)
+    }
+
+    pub fn subtrees_under(self, cap: u64) -> Result<Vec<Entry>, WalkError> {
+        let follow = self.is_following_links();
+        let mut dirs = Vec::new();
+        let mut totals: HashMap<PathBuf, u64> = HashMap::new();
+
+        for entry in self {
+            let entry = entry?;
+            let md = if follow {
+                entry.metadata()?
+            } else {
+                entry.symlink_metadata()?
+            };
+            if md.is_dir() {
+                totals.insert(entry.path().to_path_buf(), 0);
+                dirs.push(entry);
+                continue;
+            }
+            // Directories come before their contents, so every ancestor is already known.
+            for parent in entry.path().ancestors().skip(1) {
+                if let Some(total) = totals.get_mut(parent) {
+                    *total += md.len();
+                }
+            }
+        }
+
+        dirs.retain(|dir| totals[dir.path()] < cap);
+        Ok(dirs)
+    }
+}
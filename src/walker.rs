@@ -1,25 +1,94 @@
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::ffi::OsString;
 use std::fs::{self, ReadDir};
-use std::os::unix::fs::MetadataExt;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::io;
+use std::time::{Duration, SystemTime};
+use std::{io, iter, vec};
 
-use crate::{Entry, WalkOptions, WalkError};
+use crate::event::DirMarkers;
+use crate::glob::Glob;
+use crate::ignore::{self, IgnoreFile};
+use crate::platform;
+use crate::raw::RawWalk;
+use crate::sort::{Child, Sort};
+use crate::{Entry, LoopMode, MetadataErrorPolicy, WalkOptions, WalkError};
+
+type FilterFn = Box<dyn Fn(&Entry) -> bool>;
+type DescendFn = Box<dyn Fn(&Path, usize) -> bool>;
+type ErrorFn = Box<dyn FnMut(&WalkError)>;
+type StopFn = Box<dyn FnMut(&Entry) -> bool>;
+type ProgressFn = Box<dyn FnMut(usize)>;
+
+enum DirIter {
+    Read(Box<iter::Take<ReadDir>>),
+    Buffered(vec::IntoIter<Child>),
+}
+
+impl Iterator for DirIter {
+    type Item = Child;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            DirIter::Read(rd) => rd.next().map(Child::new),
+            DirIter::Buffered(it) => it.next(),
+        }
+    }
+}
 
 struct StackEntry {
-    read_dir: ReadDir,
+    path: PathBuf,
+    read_dir: DirIter,
     depth: usize,
+    raw_depth: usize,
+    gitignore: Option<IgnoreFile>,
+    walkignore: Option<IgnoreFile>,
+    via_link: bool,
+    had_children: bool,
+    // The directory's own entry, held back by `only_empty` until it is known to be empty.
+    held: Option<Entry>,
+    withheld: Option<Entry>,
 }
 
 pub struct WalkDir {
     root: PathBuf,
-    opts: WalkOptions,
+    pub(crate) roots: Vec<PathBuf>,
+    root_index: usize,
+    pub(crate) opts: WalkOptions,
     stack: Vec<StackEntry>,
-    filter: Option<Box<dyn Fn(&Entry) -> bool>>,
+    filter: Option<FilterFn>,
+    descend_if: Option<DescendFn>,
+    exclude: Vec<Glob>,
+    restrict: Vec<PathBuf>,
+    transparent: HashSet<OsString>,
+    baseline: Option<HashMap<PathBuf, (u64, SystemTime)>>,
+    #[cfg(feature = "regex")]
+    path_regex: Option<regex::Regex>,
+    on_error: Option<ErrorFn>,
+    stop_when: Option<StopFn>,
+    progress: Option<(usize, ProgressFn)>,
+    sort: Option<Sort>,
     detect_loops: bool,
     visited: HashSet<(u64, u64)>,
+    seeds: HashSet<(u64, u64)>,
+    visited_paths: HashSet<PathBuf>,
+    seen: HashSet<PathBuf>,
     started: bool,
+    finished: bool,
     root_is_file: bool,
+    bytes_seen: u64,
+    yielded: usize,
+    descents: usize,
+    dirs_opened: usize,
+    pending_dir: Option<(PathBuf, usize)>,
+    root_dev: Option<u64>,
+    canonical_root: Option<PathBuf>,
+    cwd: Option<PathBuf>,
+    dir_ends: Option<Vec<PathBuf>>,
+    last_error_kind: Option<io::ErrorKind>,
+    ancestors: VecDeque<Entry>,
 }
 
 impl WalkDir {
@@ -29,32 +98,261 @@ impl WalkDir {
         let root_is_file = md.is_file();
 
         Ok(Self {
+            roots: vec![root.clone()],
+            root_index: 0,
             root,
             opts: WalkOptions::default(),
             stack: Vec::new(),
             filter: None,
+            descend_if: None,
+            exclude: Vec::new(),
+            restrict: Vec::new(),
+            transparent: HashSet::new(),
+            baseline: None,
+            #[cfg(feature = "regex")]
+            path_regex: None,
+            on_error: None,
+            stop_when: None,
+            progress: None,
+            sort: None,
             detect_loops: true,
             visited: HashSet::new(),
+            seeds: HashSet::new(),
+            visited_paths: HashSet::new(),
+            seen: HashSet::new(),
             started: false,
+            finished: false,
             root_is_file,
+            bytes_seen: 0,
+            yielded: 0,
+            descents: 0,
+            dirs_opened: 0,
+            pending_dir: None,
+            root_dev: None,
+            canonical_root: None,
+            cwd: None,
+            dir_ends: None,
+            last_error_kind: None,
+            ancestors: VecDeque::new(),
         })
     }
 
+    pub fn new_multi<I, P>(roots: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let roots: Vec<PathBuf> = roots.into_iter().map(|r| r.as_ref().to_path_buf()).collect();
+        let Some(first) = roots.first() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no roots given"));
+        };
+        for root in &roots[1..] {
+            fs::symlink_metadata(root)?;
+        }
+
+        let mut walker = Self::new(first)?;
+        walker.roots = roots;
+        Ok(walker)
+    }
+
     pub fn follow_links(mut self, follow: bool) -> Self {
         self.opts.follow_links = follow;
         self
     }
 
+    pub fn follow_dir_links_only(mut self, yes: bool) -> Self {
+        self.opts.follow_dir_links_only = yes;
+        self
+    }
+
     pub fn max_depth(mut self, depth: usize) -> Self {
         self.opts.max_depth = depth;
         self
     }
 
+    pub fn byte_budget(mut self, bytes: u64) -> Self {
+        self.opts.byte_budget = Some(bytes);
+        self
+    }
+
+    pub fn track_root_device(mut self, yes: bool) -> Self {
+        self.opts.track_root_device = yes;
+        self
+    }
+
+    pub fn strict(mut self, yes: bool) -> Self {
+        self.opts.strict = yes;
+        self
+    }
+
+    pub fn lowercase_names(mut self, yes: bool) -> Self {
+        self.opts.lowercase_names = yes;
+        self
+    }
+
+    pub fn dedupe(mut self, yes: bool) -> Self {
+        self.opts.dedupe = yes;
+        self
+    }
+
+    pub fn owned_by(mut self, uid: u32) -> Self {
+        self.opts.uid = Some(uid);
+        self
+    }
+
+    pub fn group(mut self, gid: u32) -> Self {
+        self.opts.gid = Some(gid);
+        self
+    }
+
+    pub fn only_empty(mut self) -> Self {
+        self.opts.only_empty = true;
+        self
+    }
+
+    pub fn yield_dir_before_open(mut self, yes: bool) -> Self {
+        self.opts.yield_dir_before_open = yes;
+        self
+    }
+
+    pub fn mark_truncated(mut self, yes: bool) -> Self {
+        self.opts.mark_truncated = yes;
+        self
+    }
+
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.opts.max_file_size = Some(bytes);
+        self
+    }
+
+    pub fn older_than(mut self, age: Duration) -> Self {
+        self.opts.older_than = Some(age);
+        self
+    }
+
+    pub fn keep_dir_entries(mut self, yes: bool) -> Self {
+        self.opts.keep_dir_entries = yes;
+        self
+    }
+
+    pub fn resolve_followed_paths(mut self, yes: bool) -> Self {
+        self.opts.resolve_followed_paths = yes;
+        self
+    }
+
+    pub fn max_children(mut self, n: usize) -> Self {
+        self.opts.max_children = Some(n);
+        self
+    }
+
+    pub fn max_visited_dirs(mut self, n: usize) -> Self {
+        self.opts.max_visited_dirs = Some(n);
+        self
+    }
+
+    pub fn max_path_len(mut self, n: usize) -> Self {
+        self.opts.max_path_len = Some(n);
+        self
+    }
+
+    pub fn descent_fuel(mut self, n: usize) -> Self {
+        self.opts.descent_fuel = Some(n);
+        self
+    }
+
+    pub fn boundary_marker(mut self, name: &str) -> Self {
+        self.opts.boundary_marker = Some(name.into());
+        self
+    }
+
     pub fn detect_loops(mut self, detect: bool) -> Self {
         self.detect_loops = detect;
         self
     }
 
+    pub fn with_visited<I: IntoIterator<Item = (u64, u64)>>(mut self, v: I) -> Self {
+        self.seeds.extend(v);
+        self.visited.extend(&self.seeds);
+        self
+    }
+
+    pub fn reset(&mut self) {
+        self.root_index = 0;
+        self.root = self.roots[0].clone();
+        self.root_is_file = fs::symlink_metadata(&self.root).is_ok_and(|m| m.is_file());
+        self.stack.clear();
+        self.visited = self.seeds.clone();
+        self.visited_paths.clear();
+        self.seen.clear();
+        self.started = false;
+        self.finished = false;
+        self.bytes_seen = 0;
+        self.yielded = 0;
+        self.descents = 0;
+        self.dirs_opened = 0;
+        self.pending_dir = None;
+        self.root_dev = None;
+        self.canonical_root = None;
+        self.last_error_kind = None;
+        self.ancestors.clear();
+        if let Some(ref mut ends) = self.dir_ends {
+            ends.clear();
+        }
+        if let Some(ref mut sort) = self.sort {
+            sort.reset();
+        }
+    }
+
+    pub fn loop_detection_mode(mut self, mode: LoopMode) -> Self {
+        self.opts.loop_mode = mode;
+        self
+    }
+
+    pub fn raw_walk(self) -> RawWalk {
+        RawWalk::new(self.roots, self.opts, self.detect_loops)
+    }
+
+    pub fn with_dir_markers(mut self) -> DirMarkers {
+        self.dir_ends = Some(Vec::new());
+        DirMarkers::new(self)
+    }
+
+    pub(crate) fn take_dir_ends(&mut self) -> Vec<PathBuf> {
+        self.dir_ends.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn step(&mut self, budget: usize) -> (Vec<Result<Entry, WalkError>>, bool) {
+        let mut items = Vec::with_capacity(budget);
+        while items.len() < budget {
+            match self.next() {
+                Some(item) => items.push(item),
+                None => return (items, true),
+            }
+        }
+        (items, false)
+    }
+
+    // Not named `count` so it does not shadow `Iterator::count` on owned walkers.
+    pub fn yielded_count(&self) -> usize {
+        self.yielded
+    }
+
+    pub fn open_handles(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn is_following_links(&self) -> bool {
+        self.opts.follow_links
+    }
+
+    pub fn is_detecting_loops(&self) -> bool {
+        self.detect_loops
+    }
+
     pub fn filter_entry<F>(mut self, f: F) -> Self
     where
         F: Fn(&Entry) -> bool + 'static,
@@ -62,99 +360,720 @@ impl WalkDir {
         self.filter = Some(Box::new(f));
         self
     }
+
+    pub fn catch_filter_panics(mut self, yes: bool) -> Self {
+        self.opts.catch_filter_panics = yes;
+        self
+    }
+
+    pub fn descend_if<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Path, usize) -> bool + 'static,
+    {
+        self.descend_if = Some(Box::new(f));
+        self
+    }
+
+    pub fn exclude_globs<I: IntoIterator<Item = String>>(mut self, patterns: I) -> Self {
+        self.exclude.extend(patterns.into_iter().map(|p| Glob::new(&p)));
+        self
+    }
+
+    // Relative subpaths are taken against the root being walked.
+    pub fn restrict_to<I: IntoIterator<Item = PathBuf>>(mut self, subpaths: I) -> Self {
+        self.restrict.extend(subpaths);
+        self
+    }
+
+    #[cfg(feature = "regex")]
+    pub fn match_regex(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.path_regex = Some(regex::Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    pub fn transparent_dirs<I: IntoIterator<Item = String>>(mut self, names: I) -> Self {
+        self.transparent.extend(names.into_iter().map(OsString::from));
+        self
+    }
+
+    // Baseline keys are relative to the walk root, as in `manifest`.
+    pub fn diff_against(mut self, baseline: HashMap<PathBuf, (u64, SystemTime)>) -> Self {
+        self.baseline = Some(baseline);
+        self
+    }
+
+    pub fn respect_gitignore(mut self, yes: bool) -> Self {
+        self.opts.respect_gitignore = yes;
+        self
+    }
+
+    pub fn respect_walkignore(mut self, yes: bool) -> Self {
+        self.opts.respect_walkignore = yes;
+        self
+    }
+
+    // The working directory is captured here, not when the walk runs.
+    pub fn cwd_relative(mut self, yes: bool) -> Self {
+        self.opts.cwd_relative = yes;
+        self.cwd = None;
+        match env::current_dir() {
+            Ok(cwd) if yes => self.cwd_relative_to(cwd),
+            _ => self,
+        }
+    }
+
+    pub(crate) fn cwd_relative_to(mut self, base: PathBuf) -> Self {
+        self.opts.cwd_relative = true;
+        self.cwd = Some(base);
+        self
+    }
+
+    pub fn skip_unreadable_dirs(mut self, yes: bool) -> Self {
+        self.opts.skip_unreadable_dirs = yes;
+        self
+    }
+
+    pub fn coalesce_errors(mut self, yes: bool) -> Self {
+        self.opts.coalesce_errors = yes;
+        self
+    }
+
+    pub fn posix_relative(mut self, yes: bool) -> Self {
+        self.opts.posix_relative = yes;
+        self
+    }
+
+    pub fn confine_to_root(mut self, yes: bool) -> Self {
+        self.opts.confine_to_root = yes;
+        self
+    }
+
+    #[cfg(feature = "unicode")]
+    pub fn normalize_nfc(mut self, yes: bool) -> Self {
+        self.opts.normalize_nfc = yes;
+        self
+    }
+
+    pub fn metadata_error_policy(mut self, policy: MetadataErrorPolicy) -> Self {
+        self.opts.metadata_error_policy = policy;
+        self
+    }
+
+    pub fn skip_root(mut self, yes: bool) -> Self {
+        self.opts.skip_root = yes;
+        self
+    }
+
+    pub fn always_yield_ancestors(mut self, yes: bool) -> Self {
+        self.opts.always_yield_ancestors = yes;
+        self
+    }
+
+    pub fn on_error<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&WalkError) + 'static,
+    {
+        self.on_error = Some(Box::new(f));
+        self
+    }
+
+    pub fn stop_when<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&Entry) -> bool + 'static,
+    {
+        self.stop_when = Some(Box::new(f));
+        self
+    }
+
+    pub fn progress_every<F>(mut self, n: usize, f: F) -> Self
+    where
+        F: FnMut(usize) + 'static,
+    {
+        self.progress = Some((n, Box::new(f)));
+        self
+    }
+
+    pub fn sort_by_mtime(mut self, descending: bool) -> Self {
+        self.sort = Some(Sort::Mtime { descending });
+        self
+    }
+
+    pub fn sort_natural(mut self, yes: bool) -> Self {
+        if yes {
+            self.sort = Some(Sort::Natural);
+        } else if matches!(self.sort, Some(Sort::Natural)) {
+            self.sort = None;
+        }
+        self
+    }
+
+    pub fn recent_dirs_first(mut self, yes: bool) -> Self {
+        if yes {
+            self.sort = Some(Sort::RecentDirs);
+        } else if matches!(self.sort, Some(Sort::RecentDirs)) {
+            self.sort = None;
+        }
+        self
+    }
+
+    pub fn shuffle_children(mut self, seed: u64) -> Self {
+        // xorshift never leaves a zero state, so nudge a zero seed off it.
+        let state = if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed };
+        self.sort = Some(Sort::Shuffle { seed: state, state });
+        self
+    }
+
+    pub fn sort_by_meta<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&Entry, &fs::Metadata, &Entry, &fs::Metadata) -> Ordering + 'static,
+    {
+        self.sort = Some(Sort::Meta(Box::new(f)));
+        self
+    }
+
+    fn follows_dir_links(&self) -> bool {
+        self.opts.follow_links || self.opts.follow_dir_links_only
+    }
+
+    fn make_entry(&self, path: PathBuf, depth: usize) -> Entry {
+        Entry::new(path, depth)
+            .with_root_dev(self.root_dev)
+            .with_root_index(self.root_index)
+    }
+
+    fn stat(&self, entry: &Entry) -> io::Result<fs::Metadata> {
+        if self.opts.follow_links {
+            entry.metadata()
+        } else {
+            entry.symlink_metadata()
+        }
+    }
+
+    fn accepts(&self, entry: &Entry) -> bool {
+        let opts = &self.opts;
+        if opts.uid.is_none()
+            && opts.gid.is_none()
+            && opts.max_file_size.is_none()
+            && opts.older_than.is_none()
+            && !opts.only_empty
+        {
+            return true;
+        }
+        let Ok(md) = self.stat(entry) else {
+            return false;
+        };
+
+        if opts.uid.is_some() || opts.gid.is_some() {
+            let Some((uid, gid)) = platform::owner(&md) else {
+                return false;
+            };
+            if opts.uid.is_some_and(|u| u != uid) || opts.gid.is_some_and(|g| g != gid) {
+                return false;
+            }
+        }
+
+        if let Some(max) = opts.max_file_size
+            && md.is_file()
+            && md.len() > max
+        {
+            return false;
+        }
+
+        // Only files are aged; directories are still descended into.
+        if let Some(age) = opts.older_than {
+            if !md.is_file() {
+                return false;
+            }
+            let old = md
+                .modified()
+                .ok()
+                .and_then(|mtime| SystemTime::now().duration_since(mtime).ok())
+                .is_some_and(|elapsed| elapsed > age);
+            if !old {
+                return false;
+            }
+        }
+
+        // Directories only get this far once confirmed empty.
+        if opts.only_empty && !(md.is_dir() || (md.is_file() && md.len() == 0)) {
+            return false;
+        }
+
+        true
+    }
+
+    fn differs_from_baseline(&self, entry: &Entry) -> bool {
+        let Some(ref baseline) = self.baseline else {
+            return true;
+        };
+        let root = &self.roots[entry.root_index()];
+        let rel = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        let Some(&(size, mtime)) = baseline.get(rel) else {
+            return true;
+        };
+        match self.stat(entry) {
+            Ok(md) => md.len() != size || md.modified().ok() != Some(mtime),
+            Err(_) => true,
+        }
+    }
+
+    pub(crate) fn present(&self, mut entry: Entry) -> Entry {
+        if self.opts.resolve_followed_paths && entry.via_link() {
+            let resolved = canonical_key(entry.path());
+            entry.set_display_path(resolved);
+        }
+        if self.opts.lowercase_names {
+            // Everything below the root is lowercased, so children stay under their
+            // parent's reported path.
+            let root = &self.roots[entry.root_index()];
+            let base = if entry.path().starts_with(root) {
+                root.clone()
+            } else {
+                entry.path().parent().map(Path::to_path_buf).unwrap_or_default()
+            };
+            let mut lower = base.clone();
+            for part in entry.path().strip_prefix(&base).unwrap_or(entry.path()).components() {
+                let part = part.as_os_str();
+                match part.to_str() {
+                    Some(name) => lower.push(name.to_lowercase()),
+                    None => lower.push(part),
+                }
+            }
+            if lower != entry.path() {
+                entry.set_display_path(lower);
+            }
+        }
+        #[cfg(feature = "unicode")]
+        if self.opts.normalize_nfc
+            && let Some(name) = entry.path().file_name().and_then(|n| n.to_str())
+        {
+            use unicode_normalization::UnicodeNormalization;
+            let nfc: String = name.nfc().collect();
+            if nfc != name {
+                let path = entry.path().with_file_name(nfc);
+                entry.set_display_path(path);
+            }
+        }
+        if self.opts.posix_relative
+            && let Ok(rel) = entry.path().strip_prefix(&self.roots[entry.root_index()])
+        {
+            let mut joined = OsString::new();
+            for (i, part) in rel.components().enumerate() {
+                if i > 0 {
+                    joined.push("/");
+                }
+                joined.push(part.as_os_str());
+            }
+            if joined.is_empty() {
+                joined.push(".");
+            }
+            entry.set_display_path(PathBuf::from(joined));
+        }
+        if self.opts.cwd_relative
+            && let Some(ref cwd) = self.cwd
+            && let Ok(rel) = entry.path().strip_prefix(cwd)
+        {
+            let rel = if rel.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                rel.to_path_buf()
+            };
+            entry.set_display_path(rel);
+        }
+        entry
+    }
+
+    fn may_descend(&self, path: &Path, depth: usize) -> bool {
+        if self.opts.max_visited_dirs.is_some_and(|max| self.dirs_opened >= max) {
+            return false;
+        }
+        if self.opts.descent_fuel.is_some_and(|fuel| self.descents >= fuel) {
+            return false;
+        }
+        if let Some(ref marker) = self.opts.boundary_marker
+            && fs::metadata(path.join(marker)).is_ok_and(|m| m.is_file())
+        {
+            return false;
+        }
+        if self.opts.confine_to_root
+            && fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+        {
+            let inside = match (&self.canonical_root, fs::canonicalize(path)) {
+                (Some(root), Ok(target)) => target.starts_with(root),
+                _ => false,
+            };
+            if !inside {
+                return false;
+            }
+        }
+        match self.descend_if {
+            Some(ref f) => f(path, depth),
+            None => true,
+        }
+    }
+
+    fn push_dir(&mut self, path: &Path, depth: usize) -> io::Result<()> {
+        let rd = fs::read_dir(path)?.take(self.opts.max_children.unwrap_or(usize::MAX));
+        self.dirs_opened += 1;
+        let read_dir = match self.sort {
+            Some(ref mut sort) => {
+                let children = rd.map(Child::new).collect();
+                DirIter::Buffered(sort.apply(children, depth + 1).into_iter())
+            }
+            None => DirIter::Read(Box::new(rd)),
+        };
+        let gitignore = if self.opts.respect_gitignore {
+            IgnoreFile::load_gitignore(path)
+        } else {
+            None
+        };
+        let walkignore = if self.opts.respect_walkignore {
+            IgnoreFile::load_walkignore(path)
+        } else {
+            None
+        };
+        let via_link = self.opts.resolve_followed_paths
+            && depth > 0
+            && (self.stack.last().is_some_and(|s| s.via_link)
+                || fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()));
+        let raw_depth = self.stack.last().map_or(0, |s| s.raw_depth + 1);
+        self.stack.push(StackEntry {
+            path: path.to_path_buf(),
+            read_dir,
+            depth,
+            raw_depth,
+            gitignore,
+            walkignore,
+            via_link,
+            had_children: false,
+            held: None,
+            withheld: None,
+        });
+        Ok(())
+    }
+
+    fn open_child(&mut self, path: &Path, depth: usize) -> Result<(), WalkError> {
+        match self.push_dir(path, depth) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotADirectory => {
+                if let Some(ref mut f) = self.on_error {
+                    f(&WalkError::Io(e));
+                }
+                Ok(())
+            }
+            Err(e)
+                if e.kind() == io::ErrorKind::PermissionDenied && self.opts.skip_unreadable_dirs =>
+            {
+                Ok(())
+            }
+            Err(e) => Err(WalkError::Io(e)),
+        }
+    }
+
+    // `Some(true)` inside an allowed subtree, `Some(false)` on the way down to one.
+    fn restriction(&self, path: &Path) -> Option<bool> {
+        if self.restrict.is_empty() {
+            return Some(true);
+        }
+        let mut on_the_way = false;
+        for sub in &self.restrict {
+            let allowed = self.root.join(sub);
+            if path.starts_with(&allowed) {
+                return Some(true);
+            }
+            on_the_way |= allowed.starts_with(path);
+        }
+        on_the_way.then_some(false)
+    }
+
+    // Filtered-out directories kept on the stack are released, outermost first,
+    // ahead of the first descendant that passes.
+    fn with_ancestors(&mut self, entry: Entry) -> Option<Result<Entry, WalkError>> {
+        if !self.opts.always_yield_ancestors {
+            return Some(Ok(entry));
+        }
+        for frame in &mut self.stack {
+            if let Some(dir) = frame.withheld.take() {
+                self.ancestors.push_back(dir);
+            }
+        }
+        self.ancestors.push_back(entry);
+        self.ancestors.pop_front().map(Ok)
+    }
+
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.opts.respect_gitignore {
+            let layers = self.stack.iter().filter_map(|s| s.gitignore.as_ref());
+            if ignore::is_ignored(layers, path, is_dir) {
+                return true;
+            }
+        }
+        if self.opts.respect_walkignore {
+            let layers = self.stack.iter().filter_map(|s| s.walkignore.as_ref());
+            return ignore::is_ignored(layers, path, is_dir);
+        }
+        false
+    }
 }
 
-impl Iterator for WalkDir {
-    type Item = Result<Entry, WalkError>;
+impl WalkDir {
+    fn advance(&mut self) -> Option<Result<Entry, WalkError>> {
+        loop {
+            if let Some(item) = self.advance_root() {
+                return Some(item);
+            }
+            if self.root_index + 1 >= self.roots.len() {
+                return None;
+            }
+            self.root_index += 1;
+            self.root = self.roots[self.root_index].clone();
+            match fs::symlink_metadata(&self.root) {
+                Ok(md) => {
+                    self.root_is_file = md.is_file();
+                    self.started = false;
+                }
+                Err(e) => return Some(Err(WalkError::Io(e))),
+            }
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn advance_root(&mut self) -> Option<Result<Entry, WalkError>> {
+        if let Some(entry) = self.ancestors.pop_front() {
+            return Some(Ok(entry));
+        }
         if !self.started {
             self.started = true;
+            if self.opts.track_root_device {
+                self.root_dev = fs::metadata(&self.root).ok().and_then(|md| platform::device(&md));
+            }
+            if self.opts.confine_to_root {
+                self.canonical_root = fs::canonicalize(&self.root).ok();
+            }
             if self.root_is_file {
-                let e = Entry::new(self.root.clone(), 0);
-                if self.opts.follow_links && self.detect_loops {
-                    if let Ok(md) = e.metadata() {
-                        let dev = md.dev();
-                        let ino = md.ino();
-                        self.visited.insert((dev, ino));
-                    }
+                let e = self.make_entry(self.root.clone(), 0);
+                if self.opts.follow_links
+                    && self.detect_loops
+                    && let Ok(md) = e.metadata()
+                    && let Some(id) = platform::file_id(e.path(), &md)
+                {
+                    self.visited.insert(id);
                 }
                 return Some(Ok(e));
             } else {
-                match fs::read_dir(&self.root) {
-                    Ok(rd) => {
-                        self.stack.push(StackEntry {
-                            read_dir: rd,
-                            depth: 0,
-                        });
-                        if self.detect_loops {
-                            if let Ok(md) = fs::metadata(&self.root) {
-                                let dev = md.dev();
-                                let ino = md.ino();
-                                self.visited.insert((dev, ino));
-                            }
+                let root = self.root.clone();
+                match fs::metadata(&root) {
+                    Ok(md) if !md.is_dir() && !md.is_file() => {
+                        return Some(Err(WalkError::UnsupportedRoot(root, md.file_type())));
+                    }
+                    _ => {}
+                }
+                match self.push_dir(&root, 0) {
+                    Ok(()) => {
+                        if self.detect_loops
+                            && let Ok(md) = fs::metadata(&self.root)
+                            && let Some(id) = platform::file_id(&self.root, &md)
+                        {
+                            self.visited.insert(id);
+                        }
+                        if self.detect_loops
+                            && self.opts.loop_mode == LoopMode::Canonical
+                            && let Ok(real) = fs::canonicalize(&self.root)
+                        {
+                            self.visited_paths.insert(real);
                         }
                     }
                     Err(e) => return Some(Err(WalkError::Io(e))),
                 }
+                // A followed symlink root keeps its own path; the directory it resolves
+                // to is only its real path.
+                if self.opts.follow_links
+                    && fs::symlink_metadata(&root).is_ok_and(|m| m.file_type().is_symlink())
+                    && let Ok(resolved) = fs::canonicalize(&root)
+                {
+                    let mut entry = self.make_entry(resolved, 0);
+                    entry.set_display_path(root);
+                    return Some(Ok(entry));
+                }
             }
         }
 
+        if let Some((path, depth)) = self.pending_dir.take()
+            && let Err(e) = self.open_child(&path, depth)
+        {
+            return Some(Err(e));
+        }
+
         while let Some(top) = self.stack.last_mut() {
-            match top.read_dir.next() {
-                Some(Ok(dirent)) => {
-                    let path = dirent.path();
+            let child = top.read_dir.next();
+            top.had_children |= child.is_some();
+            match child {
+                Some(Child { dirent: Ok(dirent), md }) => {
                     let depth = top.depth + 1;
-                    let entry = Entry::new(path.clone(), depth);
+                    let raw_depth = top.raw_depth + 1;
+                    let via_link = top.via_link;
+                    let mut entry = self
+                        .make_entry(dirent.path(), depth)
+                        .with_raw_depth(raw_depth)
+                        .with_metadata(md)
+                        .with_file_type(dirent.file_type().ok());
+                    // A retained `DirEntry` pins its parent directory's fd, so only keep it on request.
+                    if self.opts.keep_dir_entries {
+                        entry = entry.with_dir_entry(dirent);
+                    }
+                    entry.set_via_link(via_link);
+                    let path = entry.path();
 
-                    if let Some(ref f) = self.filter {
-                        if !f(&entry) {
+                    let mut rejected = false;
+                    let passed = match self.filter {
+                        Some(ref f) if self.opts.catch_filter_panics => {
+                            match panic::catch_unwind(AssertUnwindSafe(|| f(&entry))) {
+                                Ok(passed) => passed,
+                                Err(_) => {
+                                    return Some(Err(WalkError::FilterPanic(path.to_path_buf())));
+                                }
+                            }
+                        }
+                        Some(ref f) => f(&entry),
+                        None => true,
+                    };
+                    if !passed {
+                        if !self.opts.always_yield_ancestors {
                             continue;
                         }
+                        rejected = true;
+                    }
+
+                    if !self.exclude.is_empty()
+                        && let Ok(rel) = path.strip_prefix(&self.root)
+                        && self.exclude.iter().any(|g| g.matches(rel))
+                    {
+                        continue;
+                    }
+
+                    let Some(inside) = self.restriction(path) else {
+                        continue;
+                    };
+
+                    if self.opts.max_path_len.is_some_and(|n| path.as_os_str().len() > n) {
+                        continue;
                     }
 
                     let is_dir_res = if self.opts.follow_links {
-                        fs::metadata(&path).map(|m| m.is_dir())
+                        platform::quick_stat(path, true).map(|st| st.is_dir)
+                    } else if self.opts.follow_dir_links_only {
+                        entry.file_type().map(|ft| {
+                            ft.is_dir()
+                                || (ft.is_symlink() && fs::metadata(path).is_ok_and(|t| t.is_dir()))
+                        })
                     } else {
-                        fs::symlink_metadata(&path).map(|m| m.is_dir())
+                        entry.file_type().map(|ft| ft.is_dir())
                     };
 
+                    if let Ok(is_dir) = is_dir_res
+                        && self.is_ignored(path, is_dir)
+                    {
+                        continue;
+                    }
+
                     return match is_dir_res {
                         Ok(true) => {
-                            if self.opts.follow_links && self.detect_loops {
-                                if let Ok(md) = fs::metadata(&path) {
-                                    let dev = md.dev();
-                                    let ino = md.ino();
-                                    if self.visited.contains(&(dev, ino)) {
-                                        continue;
-                                    } else {
-                                        self.visited.insert((dev, ino));
-                                    }
+                            let follows = self.follows_dir_links() && self.detect_loops;
+                            let canonical = follows && self.opts.loop_mode == LoopMode::Canonical;
+                            let track = follows && !canonical;
+                            if canonical
+                                && let Ok(real) = fs::canonicalize(path)
+                                && !self.visited_paths.insert(real)
+                            {
+                                continue;
+                            }
+                            if (track || !self.seeds.is_empty())
+                                && let Ok(md) = fs::metadata(path)
+                                && let Some(id) = platform::file_id(path, &md)
+                            {
+                                if self.visited.contains(&id) {
+                                    continue;
+                                } else if track {
+                                    self.visited.insert(id);
                                 }
                             }
-                            if depth <= self.opts.max_depth {
-                                match fs::read_dir(&path) {
-                                    Ok(rd) => {
-                                        self.stack.push(StackEntry { read_dir: rd, depth });
+                            if depth <= self.opts.max_depth && self.may_descend(path, depth) {
+                                self.descents += 1;
+                                // Children of a transparent directory keep its own depth.
+                                let frame_depth = match path.file_name() {
+                                    Some(name) if self.transparent.contains(name) => depth - 1,
+                                    _ => depth,
+                                };
+                                if self.opts.yield_dir_before_open
+                                    && !self.opts.only_empty
+                                    && inside
+                                    && !rejected
+                                {
+                                    self.pending_dir = Some((path.to_path_buf(), frame_depth));
+                                    entry.set_descended(true);
+                                } else {
+                                    let frames = self.stack.len();
+                                    if let Err(e) = self.open_child(path, frame_depth) {
+                                        return Some(Err(e));
                                     }
-                                    Err(e) => {
-                                        return Some(Err(WalkError::Io(e)));
+                                    entry.set_descended(self.stack.len() > frames);
+                                    if self.opts.only_empty {
+                                        if self.stack.len() > frames
+                                            && let Some(top) = self.stack.last_mut()
+                                        {
+                                            top.held = Some(entry);
+                                        }
+                                        continue;
                                     }
+                                    if rejected {
+                                        if self.stack.len() > frames
+                                            && let Some(top) = self.stack.last_mut()
+                                        {
+                                            top.withheld = Some(entry);
+                                        }
+                                        continue;
+                                    }
+                                }
+                            } else {
+                                if self.opts.only_empty
+                                    && !fs::read_dir(path).is_ok_and(|mut rd| rd.next().is_none())
+                                {
+                                    continue;
+                                }
+                                if depth > self.opts.max_depth && self.opts.mark_truncated {
+                                    entry.set_truncated(true);
                                 }
                             }
-                            Some(Ok(entry))
-                        }
-                        Ok(false) => Some(Ok(entry)),
-                        Err(e) => {
-                            Some(Err(WalkError::Io(e)))
+                            if !inside || rejected {
+                                continue;
+                            }
+                            self.with_ancestors(entry)
                         }
+                        Ok(false) if !inside || rejected => continue,
+                        Ok(false) => self.with_ancestors(entry),
+                        Err(e) => match self.opts.metadata_error_policy {
+                            MetadataErrorPolicy::Skip => continue,
+                            MetadataErrorPolicy::Yield => Some(Err(WalkError::Io(e))),
+                            MetadataErrorPolicy::Abort => {
+                                self.finished = true;
+                                Some(Err(WalkError::Io(e)))
+                            }
+                        },
                     };
                 }
-                Some(Err(e)) => {
+                Some(Child { dirent: Err(e), .. }) => {
                     return Some(Err(WalkError::Io(e)));
                 }
                 None => {
-                    self.stack.pop();
+                    if let Some(done) = self.stack.pop() {
+                        if let Some(ref mut ends) = self.dir_ends {
+                            ends.push(done.path);
+                        }
+                        if let Some(held) = done.held
+                            && !done.had_children
+                        {
+                            return Some(Ok(held));
+                        }
+                    }
                     continue;
                 }
             }
@@ -162,4 +1081,107 @@ impl Iterator for WalkDir {
 
         None
     }
-}
\ No newline at end of file
+}
+impl Iterator for WalkDir {
+    type Item = Result<Entry, WalkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let item = self.advance()?;
+
+            if let Ok(entry) = &item
+                && self.opts.skip_root
+                && entry.raw_depth() == 0
+            {
+                continue;
+            }
+
+            if let Ok(entry) = &item
+                && self.opts.dedupe
+                && !self.seen.insert(canonical_key(entry.path()))
+            {
+                continue;
+            }
+
+            if let Ok(entry) = &item
+                && !self.accepts(entry)
+            {
+                continue;
+            }
+
+            if let Ok(entry) = &item
+                && !self.differs_from_baseline(entry)
+            {
+                continue;
+            }
+
+            #[cfg(feature = "regex")]
+            if let Ok(entry) = &item
+                && let Some(ref re) = self.path_regex
+                && !re.is_match(&entry.path().to_string_lossy())
+            {
+                continue;
+            }
+
+            if self.opts.coalesce_errors {
+                let kind = match &item {
+                    Err(WalkError::Io(e)) => Some(e.kind()),
+                    _ => None,
+                };
+                if kind.is_some() && kind == self.last_error_kind {
+                    continue;
+                }
+                self.last_error_kind = kind;
+            }
+
+            if item.is_err() && self.opts.strict {
+                self.finished = true;
+            }
+
+            if let (Ok(entry), Some(budget)) = (&item, self.opts.byte_budget)
+                && let Ok(st) = platform::quick_stat(entry.real_path(), true)
+                && st.is_file
+            {
+                self.bytes_seen += st.len;
+                if self.bytes_seen > budget {
+                    self.finished = true;
+                    return None;
+                }
+            }
+
+            if item.is_ok() {
+                self.yielded += 1;
+                if let Some((every, ref mut f)) = self.progress
+                    && self.yielded.is_multiple_of(every)
+                {
+                    f(self.yielded);
+                }
+            }
+
+            let item = item.map(|entry| self.present(entry));
+
+            if let (Ok(entry), Some(stop)) = (&item, self.stop_when.as_mut())
+                && stop(entry)
+            {
+                self.finished = true;
+            }
+
+            return Some(item);
+        }
+    }
+}
+
+// Only the parent is canonicalized so an unfollowed symlink keeps its own identity.
+fn canonical_key(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => match fs::canonicalize(parent) {
+            Ok(parent) => parent.join(name),
+            Err(_) => path.to_path_buf(),
+        },
+        _ => fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+    }
+}